@@ -0,0 +1,58 @@
+use std::f64::consts::PI;
+
+use crate::{Image, Rgb, Vec3};
+
+// What rays that miss every object see
+pub enum Background {
+    Solid(Rgb),
+    // blends from `bottom` straight down to `top` straight up
+    Gradient { bottom: Rgb, top: Rgb },
+    // faces in the order +x, -x, +y, -y, +z, -z, laid out as OpenGL expects them
+    CubeMap(Box<[Image; 6]>),
+    // longitude across, latitude down, with +z in the middle
+    Equirectangular(Image),
+}
+
+impl Background {
+    pub fn sample(&self, direction: Vec3) -> Rgb {
+        let d = direction.unit();
+
+        match self {
+            Background::Solid(color) => *color,
+            Background::Gradient { bottom, top } => {
+                let t = (d.1 + 1.0) / 2.0;
+                *bottom * (1.0 - t) + *top * t
+            }
+            Background::CubeMap(faces) => {
+                let (x, y, z) = (d.0, d.1, d.2);
+                let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+
+                // face, then the coordinates across it and its major axis
+                let (face, s, t, major) = if ax >= ay && ax >= az {
+                    if x > 0.0 {
+                        (0, -z, -y, ax)
+                    } else {
+                        (1, z, -y, ax)
+                    }
+                } else if ay >= az {
+                    if y > 0.0 {
+                        (2, x, z, ay)
+                    } else {
+                        (3, x, -z, ay)
+                    }
+                } else if z > 0.0 {
+                    (4, x, -y, az)
+                } else {
+                    (5, -x, -y, az)
+                };
+
+                faces[face].sample((s / major + 1.0) / 2.0, (t / major + 1.0) / 2.0)
+            }
+            Background::Equirectangular(image) => {
+                let u = 0.5 + d.0.atan2(d.2) / (2.0 * PI);
+                let v = d.1.clamp(-1.0, 1.0).acos() / PI;
+                image.sample(u, v)
+            }
+        }
+    }
+}
@@ -1,6 +1,17 @@
-use cgfs::{Canvas, Rasterizer};
+use std::process;
+
+use cgfs::{Options, Rasterizer};
 pub fn main() {
-    let mut canvas = Canvas::new("Computer Graphics from Scratch - Raytracer", 800, 800);
+    let options = Options::from_args().unwrap_or_else(|error| {
+        eprintln!("{error}\n\n{}", Options::USAGE);
+        process::exit(2);
+    });
     let mut rasterizer = Rasterizer::default_scene();
-    canvas.render(&mut rasterizer);
+    if let Err(error) = options.run(
+        "Computer Graphics from Scratch - Rasterizer",
+        &mut rasterizer,
+    ) {
+        eprintln!("{error}");
+        process::exit(1);
+    }
 }
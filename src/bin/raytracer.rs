@@ -1,6 +1,14 @@
-use cgfs::{Canvas, Raytracer};
+use std::process;
+
+use cgfs::{Options, Raytracer};
 pub fn main() {
-    let mut canvas = Canvas::new("Computer Graphics from Scratch - Raytracer", 800, 800);
-    let mut raytracer = Raytracer::DEFAULT_SCENE;
-    canvas.render(&mut raytracer);
+    let options = Options::from_args().unwrap_or_else(|error| {
+        eprintln!("{error}\n\n{}", Options::USAGE);
+        process::exit(2);
+    });
+    let mut raytracer = Raytracer::default_scene();
+    if let Err(error) = options.run("Computer Graphics from Scratch - Raytracer", &mut raytracer) {
+        eprintln!("{error}");
+        process::exit(1);
+    }
 }
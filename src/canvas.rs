@@ -1,43 +1,92 @@
-use crate::Color;
+use std::io;
+use std::time::Instant;
+
+use crate::export::FrameWriter;
+use crate::input::{Action, Bindings, FrameInput, InputState, Recording, ScrollMode};
+use crate::{Color, Rgb, Vec3};
 use minifb::{Key, Window, WindowOptions};
 
 pub struct Canvas {
     width: usize,
     height: usize,
-    window: Window,
+    window: Option<Window>,
     buffer: Vec<u32>,
     depth_buffer: Vec<f64>,
+    accumulation: Vec<Rgb>,
+    pass: u32,
+    passes: u32,
+    block: usize,
+    movement_speed: f64,
+    rotation_speed: f64,
+    input: InputState,
 }
 
 pub trait Renderer {
     fn render(&self, canvas: &mut Canvas);
 
-    fn move_up(&mut self);
-    fn move_down(&mut self);
-    fn move_left(&mut self);
-    fn move_right(&mut self);
-    fn move_front(&mut self);
-    fn move_back(&mut self);
+    // relative to the camera heading: x right, y up, z front
+    fn translate(&mut self, offset: Vec3);
+    // degrees, positive yaw turns right and positive pitch looks up
+    fn rotate(&mut self, yaw: f64, pitch: f64);
+    // scales the field of view, factors below 1 zoom in
+    fn zoom(&mut self, factor: f64);
+
+    // switches to the renderer's next shading or integration mode
+    fn next_mode(&mut self) {}
+
+    // switches between drawing surfaces, their edges and their vertices
+    fn next_draw_mode(&mut self) {}
+
+    // whether more passes keep refining the same view
+    fn progressive(&self) -> bool {
+        false
+    }
+
+    // whether render honours the canvas block size, so new views can be shown coarse first
+    fn previews(&self) -> bool {
+        false
+    }
 
-    fn rotate_left(&mut self);
-    fn rotate_right(&mut self);
+    // focuses the camera on what the canvas point shows, returning whether the view changed
+    fn focus(&mut self, _canvas: &Canvas, _x: i32, _y: i32) -> bool {
+        false
+    }
 }
 
 const BACKGROUND_COLOR: u32 = 0x00_FF_FF_FF;
+// block size of the first pass after the view changes, halved by each following pass
+const PREVIEW_BLOCK: usize = 8;
 
 impl Canvas {
     pub fn new(title: &str, width: usize, height: usize) -> Self {
-        let buffer = vec![BACKGROUND_COLOR; width * height];
-        let depth_buffer = vec![f64::INFINITY; width * height];
         let mut window = Window::new(title, width, height, WindowOptions::default()).unwrap();
         window.limit_update_rate(Some(std::time::Duration::from_micros(16600)));
 
+        Self {
+            window: Some(window),
+            ..Self::headless(width, height)
+        }
+    }
+
+    // offscreen canvas for batch rendering, nothing is ever shown
+    pub fn headless(width: usize, height: usize) -> Self {
+        let buffer = vec![BACKGROUND_COLOR; width * height];
+        let depth_buffer = vec![f64::INFINITY; width * height];
+        let accumulation = vec![Rgb::BLACK; width * height];
+
         Self {
             width,
             height,
-            window,
+            window: None,
             buffer,
             depth_buffer,
+            accumulation,
+            pass: 0,
+            passes: 64,
+            block: 1,
+            movement_speed: 3.0,
+            rotation_speed: 90.0,
+            input: InputState::new(Bindings::default()),
         }
     }
 
@@ -49,7 +98,53 @@ impl Canvas {
         self.height
     }
 
-    pub fn put_pixel(&mut self, x: i32, y: i32, color: Color) {
+    // units per second
+    pub fn set_movement_speed(&mut self, speed: f64) {
+        self.movement_speed = speed;
+    }
+
+    // degrees per second
+    pub fn set_rotation_speed(&mut self, speed: f64) {
+        self.rotation_speed = speed;
+    }
+
+    // passes a progressive renderer accumulates for each finished frame
+    pub fn set_passes(&mut self, passes: u32) {
+        self.passes = passes.max(1);
+    }
+
+    // passes rendered since the view last changed
+    pub fn pass(&self) -> u32 {
+        self.pass
+    }
+
+    // side of the square a single sample covers in this pass, 1 at full resolution
+    pub fn block_size(&self) -> usize {
+        self.block
+    }
+
+    // Whether the pixel at the corner of a block needs a sample this pass. Preview passes
+    // skip the corners an earlier, coarser pass already drew.
+    pub fn needs_sample(&self, x: i32, y: i32) -> bool {
+        let coarser = 2 * self.block as i32;
+        self.block == 1
+            || self.block == PREVIEW_BLOCK
+            || (x + self.width as i32 / 2) % coarser != 0
+            || (y + self.height as i32 / 2) % coarser != 0
+    }
+
+    pub fn set_bindings(&mut self, bindings: Bindings) {
+        self.input.bindings = bindings;
+    }
+
+    // 0x00RRGGBB, row-major from the top-left corner
+    pub fn pixels(&self) -> &[u32] {
+        &self.buffer
+    }
+
+    // index into the buffers of (x, y), with the origin in the middle and y up; None outside
+    // the canvas
+    pub(crate) fn offset(&self, x: i32, y: i32) -> Option<usize> {
         let width = self.width as i32;
         let height = self.height as i32;
 
@@ -57,26 +152,44 @@ impl Canvas {
         let sy = height / 2 - y - 1;
 
         if sx < 0 || sx >= width || sy < 0 || sy >= height {
-            return;
+            return None;
         }
 
-        let c = (color.0 as u32) << 16 | (color.1 as u32) << 8 | (color.2 as u32);
+        Some(sy as usize * self.width + sx as usize)
+    }
 
-        self.buffer[sy as usize * self.width + sx as usize] = c;
+    pub fn put_pixel(&mut self, x: i32, y: i32, color: Color) {
+        if let Some(offset) = self.offset(x, y) {
+            self.buffer[offset] = pack(color);
+        }
     }
 
-    pub fn update_depth_buffer(&mut self, x: i32, y: i32, z: f64) -> bool {
-        let width = self.width as i32;
-        let height = self.height as i32;
+    // fills the block whose bottom-left corner is (x, y)
+    pub fn put_block(&mut self, x: i32, y: i32, color: Color) {
+        let block = self.block as i32;
+        for bx in x..x + block {
+            for by in y..y + block {
+                self.put_pixel(bx, by, color);
+            }
+        }
+    }
 
-        let sx = width / 2 + x;
-        let sy = height / 2 - y - 1;
+    // adds a sample to the pixel, which then shows the mean of its samples since reset
+    pub fn accumulate_pixel(&mut self, x: i32, y: i32, color: Rgb) {
+        let Some(offset) = self.offset(x, y) else {
+            return;
+        };
 
-        if sx < 0 || sx >= width || sy < 0 || sy >= height {
+        let sum = self.accumulation[offset] + color;
+        self.accumulation[offset] = sum;
+        self.put_pixel(x, y, (sum * (1.0 / (self.pass + 1) as f64)).to_color());
+    }
+
+    pub fn update_depth_buffer(&mut self, x: i32, y: i32, z: f64) -> bool {
+        let Some(offset) = self.offset(x, y) else {
             return false;
-        }
+        };
 
-        let offset = sy as usize * self.width + sx as usize;
         if z < self.depth_buffer[offset] {
             self.depth_buffer[offset] = z;
             true
@@ -85,58 +198,237 @@ impl Canvas {
         }
     }
 
-    fn reset(&mut self) {
+    pub fn reset(&mut self) {
         self.buffer.fill(BACKGROUND_COLOR);
         self.depth_buffer.fill(f64::INFINITY);
+        self.accumulation.fill(Rgb::BLACK);
+        self.pass = 0;
+        self.block = 1;
     }
 
-    pub fn render(&mut self, renderer: &mut impl Renderer) {
+    // preview passes don't count towards the accumulated samples
+    fn render_pass(&mut self, renderer: &impl Renderer) {
         renderer.render(self);
+        if self.block == 1 {
+            self.pass += 1;
+        }
+    }
 
-        while self.window.is_open() && !self.window.is_key_down(Key::Escape) {
-            if self.window.is_key_down(Key::W) {
-                renderer.move_front();
-                self.reset();
-                renderer.render(self);
-            }
-            if self.window.is_key_down(Key::S) {
-                renderer.move_back();
-                self.reset();
-                renderer.render(self);
-            }
-            if self.window.is_key_down(Key::A) {
-                if self.window.is_key_down(Key::LeftShift) {
-                    renderer.rotate_left();
-                } else {
-                    renderer.move_left();
-                }
-                self.reset();
-                renderer.render(self);
+    // starts a new view with a quick coarse pass when the renderer supports it
+    fn begin_view(&mut self, renderer: &impl Renderer) {
+        self.reset();
+        if renderer.previews() {
+            self.block = PREVIEW_BLOCK;
+        }
+        self.render_pass(renderer);
+    }
+
+    // sharpens the preview, then keeps accumulating samples while progressive
+    fn refine(&mut self, renderer: &impl Renderer) {
+        if self.block > 1 {
+            self.block /= 2;
+            self.render_pass(renderer);
+        } else if renderer.progressive() && self.pass < self.passes {
+            self.render_pass(renderer);
+        }
+    }
+
+    // renders the view from scratch, with all its passes when progressive
+    pub fn render_still(&mut self, renderer: &impl Renderer) {
+        self.reset();
+        self.render_pass(renderer);
+        while renderer.progressive() && self.pass < self.passes {
+            self.render_pass(renderer);
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        self.window
+            .as_ref()
+            .is_some_and(|window| window.is_open() && !window.is_key_down(Key::Escape))
+    }
+
+    fn present(&mut self) {
+        if let Some(window) = &mut self.window {
+            window
+                .update_with_buffer(&self.buffer, self.width, self.height)
+                .unwrap();
+        }
+    }
+
+    // returns whether the view changed
+    fn apply_input(&mut self, input: &FrameInput, renderer: &mut impl Renderer) -> bool {
+        let distance = self.movement_speed * input.elapsed;
+        let degrees = self.rotation_speed * input.elapsed;
+
+        let mut offset = Vec3(0.0, 0.0, 0.0);
+        let (mut yaw, mut pitch) = input.look;
+        let mut zoom = 1.0;
+        let mut speed = 1.0;
+
+        for action in &input.actions {
+            match action {
+                Action::MoveFront => offset.2 += distance,
+                Action::MoveBack => offset.2 -= distance,
+                Action::MoveLeft => offset.0 -= distance,
+                Action::MoveRight => offset.0 += distance,
+                Action::MoveUp => offset.1 += distance,
+                Action::MoveDown => offset.1 -= distance,
+                Action::TurnLeft => yaw -= degrees,
+                Action::TurnRight => yaw += degrees,
+                Action::LookUp => pitch += degrees,
+                Action::LookDown => pitch -= degrees,
+                // double or halve per second held
+                Action::ZoomIn => zoom *= 0.5f64.powf(input.elapsed),
+                Action::ZoomOut => zoom *= 2.0f64.powf(input.elapsed),
+                Action::Faster => speed *= 2.0f64.powf(input.elapsed),
+                Action::Slower => speed *= 0.5f64.powf(input.elapsed),
+                Action::NextMode | Action::NextDrawMode => {}
             }
-            if self.window.is_key_down(Key::D) {
-                if self.window.is_key_down(Key::LeftShift) {
-                    renderer.rotate_right();
-                } else {
-                    renderer.move_right();
-                }
-                self.reset();
-                renderer.render(self);
+        }
+
+        match self.input.bindings.scroll {
+            ScrollMode::Speed => speed *= 1.1f64.powf(input.scroll),
+            ScrollMode::Fov => zoom *= 0.9f64.powf(input.scroll),
+        }
+        self.movement_speed *= speed;
+
+        let mut changed = false;
+        for action in &input.actions {
+            match action {
+                Action::NextMode => renderer.next_mode(),
+                Action::NextDrawMode => renderer.next_draw_mode(),
+                _ => continue,
             }
+            changed = true;
+        }
+        if offset.length() > 0.0 {
+            renderer.translate(offset);
+            changed = true;
+        }
+        if yaw != 0.0 || pitch != 0.0 {
+            renderer.rotate(yaw, pitch);
+            changed = true;
+        }
+        if zoom != 1.0 {
+            renderer.zoom(zoom);
+            changed = true;
+        }
+        if let Some((x, y)) = input.focus {
+            changed |= renderer.focus(self, x, y);
+        }
+        changed
+    }
+
+    pub fn render(&mut self, renderer: &mut impl Renderer) {
+        self.interact(renderer, |_| {});
+    }
+
+    // like render, returning the input so it can be replayed later
+    pub fn record(&mut self, renderer: &mut impl Renderer) -> Recording {
+        let mut recording = Recording {
+            size: (self.width, self.height),
+            samples: self.passes,
+            movement_speed: self.movement_speed,
+            rotation_speed: self.rotation_speed,
+            scroll: self.input.bindings.scroll,
+            frames: Vec::new(),
+        };
+        self.interact(renderer, |input| recording.frames.push(input.clone()));
+
+        recording
+    }
 
-            if self.window.is_key_down(Key::Up) {
-                renderer.move_up();
-                self.reset();
-                renderer.render(self);
+    fn interact(&mut self, renderer: &mut impl Renderer, mut on_input: impl FnMut(&FrameInput)) {
+        self.begin_view(renderer);
+
+        let mut last_frame = Instant::now();
+        while self.is_open() {
+            // scale by the time the previous frame took so speed doesn't depend on render time
+            let elapsed = last_frame.elapsed().as_secs_f64();
+            last_frame = Instant::now();
+
+            let Some(window) = &self.window else { break };
+            let input = self.input.poll(window, elapsed);
+            on_input(&input);
+
+            if self.apply_input(&input, renderer) {
+                self.begin_view(renderer);
+            } else {
+                // refine while the view stays still
+                self.refine(renderer);
             }
+            self.present();
+        }
+    }
+
+    // Feeds recorded input to the renderer, which must start from the same scene as the
+    // recording did, with the recorded sample count. Works headless too; every frame,
+    // including the initial one, goes to `writer` when given. Canvases of another size than
+    // the recording's are refused, as focus clicks are canvas points.
+    pub fn replay(
+        &mut self,
+        renderer: &mut impl Renderer,
+        recording: &Recording,
+        mut writer: Option<&mut dyn FrameWriter>,
+    ) -> io::Result<()> {
+        if recording.size != (self.width, self.height) {
+            let (width, height) = recording.size;
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "recording was made at {width}x{height}, not {}x{}",
+                    self.width, self.height
+                ),
+            ));
+        }
+        self.set_passes(recording.samples);
+        self.movement_speed = recording.movement_speed;
+        self.rotation_speed = recording.rotation_speed;
+        self.input.bindings.scroll = recording.scroll;
 
-            if self.window.is_key_down(Key::Down) {
-                renderer.move_down();
-                self.reset();
-                renderer.render(self);
+        self.render_still(renderer);
+        self.present();
+        if let Some(writer) = writer.as_mut() {
+            writer.write_frame(self)?;
+        }
+
+        for input in &recording.frames {
+            if self.apply_input(input, renderer) {
+                self.render_still(renderer);
             }
-            self.window
-                .update_with_buffer(&self.buffer, self.width, self.height)
-                .unwrap();
+            self.present();
+            if let Some(writer) = writer.as_mut() {
+                writer.write_frame(self)?;
+            }
+        }
+
+        match writer {
+            Some(writer) => writer.finish(),
+            None => Ok(()),
         }
     }
+
+    // renders `frames` frames, letting `animate` advance the scene or camera before each one
+    pub fn render_frames<R: Renderer>(
+        &mut self,
+        renderer: &mut R,
+        frames: usize,
+        mut animate: impl FnMut(&mut R, usize),
+        writer: &mut dyn FrameWriter,
+    ) -> io::Result<()> {
+        for frame in 0..frames {
+            animate(renderer, frame);
+            self.render_still(renderer);
+            self.present();
+            writer.write_frame(self)?;
+        }
+
+        writer.finish()
+    }
+}
+
+// 0x00RRGGBB, the layout of the pixel buffer
+pub(crate) fn pack(color: Color) -> u32 {
+    (color.0 as u32) << 16 | (color.1 as u32) << 8 | (color.2 as u32)
 }
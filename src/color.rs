@@ -1,4 +1,4 @@
-use std::ops::{Add, Mul};
+use std::ops::{Add, Mul, Sub};
 
 #[derive(Clone, Copy)]
 pub struct Color(pub u8, pub u8, pub u8);
@@ -48,3 +48,95 @@ impl Mul<Color> for f64 {
         rhs * self
     }
 }
+
+// tints by light of the given color
+impl Mul<Rgb> for Color {
+    type Output = Self;
+
+    fn mul(self, rhs: Rgb) -> Self::Output {
+        let r = self.0 as f64 * rhs.0;
+        let g = self.1 as f64 * rhs.1;
+        let b = self.2 as f64 * rhs.2;
+
+        Self(
+            r.clamp(0.0, 255.0) as u8,
+            g.clamp(0.0, 255.0) as u8,
+            b.clamp(0.0, 255.0) as u8,
+        )
+    }
+}
+
+// linear floating point color for accumulating light, where 1.0 maps to 255
+#[derive(Clone, Copy)]
+pub struct Rgb(pub f64, pub f64, pub f64);
+
+impl Rgb {
+    pub const BLACK: Self = Self(0.0, 0.0, 0.0);
+    pub const WHITE: Self = Self(1.0, 1.0, 1.0);
+
+    pub const fn gray(value: f64) -> Self {
+        Self(value, value, value)
+    }
+
+    pub fn max_component(self) -> f64 {
+        self.0.max(self.1).max(self.2)
+    }
+
+    pub fn to_color(self) -> Color {
+        Color(
+            (self.0 * 255.0).round().clamp(0.0, 255.0) as u8,
+            (self.1 * 255.0).round().clamp(0.0, 255.0) as u8,
+            (self.2 * 255.0).round().clamp(0.0, 255.0) as u8,
+        )
+    }
+}
+
+impl From<Color> for Rgb {
+    fn from(color: Color) -> Self {
+        Self(
+            color.0 as f64 / 255.0,
+            color.1 as f64 / 255.0,
+            color.2 as f64 / 255.0,
+        )
+    }
+}
+
+impl Add for Rgb {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self(self.0 + rhs.0, self.1 + rhs.1, self.2 + rhs.2)
+    }
+}
+
+impl Sub for Rgb {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self(self.0 - rhs.0, self.1 - rhs.1, self.2 - rhs.2)
+    }
+}
+
+impl Mul for Rgb {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self(self.0 * rhs.0, self.1 * rhs.1, self.2 * rhs.2)
+    }
+}
+
+impl Mul<f64> for Rgb {
+    type Output = Self;
+
+    fn mul(self, rhs: f64) -> Self::Output {
+        Self(self.0 * rhs, self.1 * rhs, self.2 * rhs)
+    }
+}
+
+impl Mul<Rgb> for f64 {
+    type Output = Rgb;
+
+    fn mul(self, rhs: Rgb) -> Self::Output {
+        rhs * self
+    }
+}
@@ -0,0 +1,202 @@
+use crate::canvas::pack;
+use crate::{Canvas, Color};
+
+// 2D drawing in canvas coordinates, with the origin in the middle and y growing upwards.
+// Shapes include their edges and get cut off at the borders.
+impl Canvas {
+    // one pixel wide line, both ends included
+    pub fn draw_line(&mut self, from: (i32, i32), to: (i32, i32), color: Color) {
+        for (x, y) in line(from.0, from.1, to.0, to.1) {
+            self.put_pixel(x, y, color);
+        }
+    }
+
+    // every pixel within width / 2 of the segment, which rounds the ends
+    pub fn draw_thick_line(&mut self, from: (i32, i32), to: (i32, i32), width: f64, color: Color) {
+        let reach = (width / 2.0).max(0.5);
+        let margin = reach.ceil() as i32;
+        let (ax, ay) = (from.0 as f64, from.1 as f64);
+        let (dx, dy) = ((to.0 - from.0) as f64, (to.1 - from.1) as f64);
+        let length2 = dx * dx + dy * dy;
+
+        for y in from.1.min(to.1) - margin..=from.1.max(to.1) + margin {
+            for x in from.0.min(to.0) - margin..=from.0.max(to.0) + margin {
+                let (px, py) = (x as f64 - ax, y as f64 - ay);
+                let t = if length2 > 0.0 {
+                    ((px * dx + py * dy) / length2).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let (ex, ey) = (px - t * dx, py - t * dy);
+                if ex * ex + ey * ey <= reach * reach {
+                    self.put_pixel(x, y, color);
+                }
+            }
+        }
+    }
+
+    // lines joining the points in turn
+    pub fn draw_polyline(&mut self, points: &[(i32, i32)], color: Color) {
+        if let [point] = points {
+            self.put_pixel(point.0, point.1, color);
+        }
+        for pair in points.windows(2) {
+            self.draw_line(pair[0], pair[1], color);
+        }
+    }
+
+    pub fn draw_triangle(&mut self, p0: (i32, i32), p1: (i32, i32), p2: (i32, i32), color: Color) {
+        self.draw_polyline(&[p0, p1, p2, p0], color);
+    }
+
+    // pixels on the inside of all three edges, whichever way round the corners go
+    pub fn fill_triangle(&mut self, p0: (i32, i32), p1: (i32, i32), p2: (i32, i32), color: Color) {
+        let edge = |a: (i32, i32), b: (i32, i32), x: i32, y: i32| {
+            (b.0 - a.0) as i64 * (y - a.1) as i64 - (b.1 - a.1) as i64 * (x - a.0) as i64
+        };
+        if edge(p0, p1, p2.0, p2.1) == 0 {
+            // no area, but still the line it collapses to
+            self.draw_polyline(&[p0, p1, p2], color);
+            return;
+        }
+
+        let (min_x, max_x) = (p0.0.min(p1.0).min(p2.0), p0.0.max(p1.0).max(p2.0));
+        let (min_y, max_y) = (p0.1.min(p1.1).min(p2.1), p0.1.max(p1.1).max(p2.1));
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                let w = [edge(p1, p2, x, y), edge(p2, p0, x, y), edge(p0, p1, x, y)];
+                if w.iter().all(|w| *w >= 0) || w.iter().all(|w| *w <= 0) {
+                    self.put_pixel(x, y, color);
+                }
+            }
+        }
+    }
+
+    // outline of the `width` x `height` rectangle whose bottom-left corner is `corner`
+    pub fn draw_rect(&mut self, corner: (i32, i32), width: i32, height: i32, color: Color) {
+        if width <= 0 || height <= 0 {
+            return;
+        }
+        let (left, bottom) = corner;
+        let (right, top) = (left + width - 1, bottom + height - 1);
+        self.draw_polyline(
+            &[
+                (left, bottom),
+                (right, bottom),
+                (right, top),
+                (left, top),
+                (left, bottom),
+            ],
+            color,
+        );
+    }
+
+    pub fn fill_rect(&mut self, corner: (i32, i32), width: i32, height: i32, color: Color) {
+        for y in corner.1..corner.1 + height {
+            for x in corner.0..corner.0 + width {
+                self.put_pixel(x, y, color);
+            }
+        }
+    }
+
+    // midpoint circle, eight octants at a time
+    pub fn draw_circle(&mut self, center: (i32, i32), radius: i32, color: Color) {
+        let (mut x, mut y) = (radius, 0);
+        let mut error = 1 - radius;
+        while x >= y {
+            for (dx, dy) in [
+                (x, y),
+                (y, x),
+                (-y, x),
+                (-x, y),
+                (-x, -y),
+                (-y, -x),
+                (y, -x),
+                (x, -y),
+            ] {
+                self.put_pixel(center.0 + dx, center.1 + dy, color);
+            }
+            y += 1;
+            if error < 0 {
+                error += 2 * y + 1;
+            } else {
+                x -= 1;
+                error += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    // the circle's outline and every pixel within it
+    pub fn fill_circle(&mut self, center: (i32, i32), radius: i32, color: Color) {
+        let (mut x, mut y) = (radius, 0);
+        let mut error = 1 - radius;
+        while x >= y {
+            for (half, dy) in [(x, y), (x, -y), (y, x), (y, -x)] {
+                for dx in -half..=half {
+                    self.put_pixel(center.0 + dx, center.1 + dy, color);
+                }
+            }
+            y += 1;
+            if error < 0 {
+                error += 2 * y + 1;
+            } else {
+                x -= 1;
+                error += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    // recolors the area of the same color as `seed` that joins it sideways or up and down
+    pub fn flood_fill(&mut self, seed: (i32, i32), color: Color) {
+        let Some(target) = self.pixel_at(seed.0, seed.1) else {
+            return;
+        };
+        if target == pack(color) {
+            return;
+        }
+
+        let mut stack = vec![seed];
+        while let Some((x, y)) = stack.pop() {
+            if self.pixel_at(x, y) != Some(target) {
+                continue;
+            }
+            self.put_pixel(x, y, color);
+            stack.extend([(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)]);
+        }
+    }
+
+    // packed like `pixels`, None outside the canvas
+    fn pixel_at(&self, x: i32, y: i32) -> Option<u32> {
+        self.offset(x, y).map(|offset| self.pixels()[offset])
+    }
+}
+
+// Bresenham's points from (x0, y0) to (x1, y1), in that order
+pub(crate) fn line(x0: i32, y0: i32, x1: i32, y1: i32) -> impl Iterator<Item = (i32, i32)> {
+    let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+    let (step_x, step_y) = ((x1 - x0).signum(), (y1 - y0).signum());
+    let (mut x, mut y) = (x0, y0);
+    let mut error = dx + dy;
+    let mut done = false;
+
+    std::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+        let point = (x, y);
+        if x == x1 && y == y1 {
+            done = true;
+        } else {
+            let twice = 2 * error;
+            if twice >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if twice <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+        Some(point)
+    })
+}
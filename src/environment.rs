@@ -0,0 +1,182 @@
+use std::f64::consts::PI;
+
+use crate::random::Rng;
+use crate::{Image, Rgb, Vec3};
+
+// Roughness of each prefiltered specular level, the first being the map itself
+const SPECULAR_LEVELS: [f64; 5] = [0.0, 0.25, 0.5, 0.75, 1.0];
+
+// Equirectangular radiance map used as a light source, laid out like
+// `Background::Equirectangular`. Building it prepares what both renderers need: a distribution
+// for importance sampling bright regions in the raytracer, and prefiltered irradiance and
+// specular maps for the rasterizer.
+pub struct EnvironmentMap {
+    image: Image,
+    rows: Vec<f64>,         // cumulative distribution over rows
+    columns: Vec<Vec<f64>>, // cumulative distribution over the columns of each row
+    total: f64,             // sum of the sampling weights
+    irradiance: Image,
+    specular: Vec<Image>,
+}
+
+impl EnvironmentMap {
+    pub fn new(image: Image) -> Self {
+        let (width, height) = (image.width(), image.height());
+
+        // texels near the poles cover less of the sphere
+        let mut rows = Vec::with_capacity(height);
+        let mut columns = Vec::with_capacity(height);
+        let mut total = 0.0;
+        for y in 0..height {
+            let sin = ((y as f64 + 0.5) / height as f64 * PI).sin();
+            let mut row = Vec::with_capacity(width);
+            let mut sum = 0.0;
+            for x in 0..width {
+                sum += (luminance(image.pixel(x, y)) + 1e-6) * sin;
+                row.push(sum);
+            }
+            total += sum;
+            rows.push(total);
+            columns.push(row);
+        }
+
+        let source = image.downsampled(64, 32);
+        let irradiance = convolve(&source, 32, 16, |cos| cos.max(0.0));
+        let specular = SPECULAR_LEVELS
+            .iter()
+            .map(|&roughness| {
+                if roughness == 0.0 {
+                    return image.downsampled(256, 128);
+                }
+                // Phong lobe with roughly the spread of GGX at this roughness
+                let alpha = roughness * roughness;
+                let exponent = 2.0 / (alpha * alpha) - 2.0;
+                convolve(&source, 64, 32, |cos| cos.max(0.0).powf(exponent))
+            })
+            .collect();
+
+        Self {
+            image,
+            rows,
+            columns,
+            total,
+            irradiance,
+            specular,
+        }
+    }
+
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    pub fn radiance(&self, direction: Vec3) -> Rgb {
+        let (u, v) = to_uv(direction);
+        self.image.sample(u, v)
+    }
+
+    // Mean radiance over the hemisphere around `normal`, weighted by the cosine: the
+    // intensity an ambient light would need to light a diffuse surface the same way
+    pub fn irradiance(&self, normal: Vec3) -> Rgb {
+        let (u, v) = to_uv(normal);
+        self.irradiance.sample(u, v)
+    }
+
+    // radiance around `direction` blurred as a surface of the given roughness would
+    pub fn specular(&self, direction: Vec3, roughness: f64) -> Rgb {
+        let (u, v) = to_uv(direction);
+        let level = roughness.clamp(0.0, 1.0) * (SPECULAR_LEVELS.len() - 1) as f64;
+        let (lower, t) = (level.floor() as usize, level.fract());
+        let sample = |level: usize| self.specular[level].sample(u, v);
+
+        if lower + 1 < SPECULAR_LEVELS.len() {
+            sample(lower) * (1.0 - t) + sample(lower + 1) * t
+        } else {
+            sample(lower)
+        }
+    }
+
+    // Direction towards the environment, chosen in proportion to its brightness, and the
+    // radiance from there divided by the probability density of having picked it. The
+    // radiance is the unfiltered texel's so the ratio stays bounded next to bright spots.
+    pub(crate) fn sample(&self, rng: &mut Rng) -> (Vec3, Rgb) {
+        let (width, height) = (self.image.width(), self.image.height());
+        // an empty map gives no light
+        if self.image.is_empty() {
+            return (Vec3(0.0, 1.0, 0.0), Rgb::BLACK);
+        }
+        let y = pick(&self.rows, rng.next() * self.total);
+        let row = &self.columns[y];
+        let x = pick(row, rng.next() * row[width - 1]);
+
+        let u = (x as f64 + rng.next()) / width as f64;
+        let v = (y as f64 + rng.next()) / height as f64;
+
+        // the texel's weight spread over its solid angle, whose sine cancels the one in the weight
+        let weight = row[x] - if x > 0 { row[x - 1] } else { 0.0 };
+        let sin = ((y as f64 + 0.5) / height as f64 * PI).sin();
+        let pdf = weight / sin * (width * height) as f64 / (self.total * 2.0 * PI * PI);
+
+        (from_uv(u, v), self.image.pixel(x, y) * (1.0 / pdf))
+    }
+}
+
+fn luminance(color: Rgb) -> f64 {
+    0.2126 * color.0 + 0.7152 * color.1 + 0.0722 * color.2
+}
+
+fn to_uv(direction: Vec3) -> (f64, f64) {
+    let d = direction.unit();
+    (
+        0.5 + d.0.atan2(d.2) / (2.0 * PI),
+        d.1.clamp(-1.0, 1.0).acos() / PI,
+    )
+}
+
+fn from_uv(u: f64, v: f64) -> Vec3 {
+    let (phi, theta) = ((u - 0.5) * 2.0 * PI, v * PI);
+    Vec3(
+        theta.sin() * phi.sin(),
+        theta.cos(),
+        theta.sin() * phi.cos(),
+    )
+}
+
+// first index whose cumulative value exceeds `value`
+fn pick(cumulative: &[f64], value: f64) -> usize {
+    cumulative
+        .partition_point(|c| *c <= value)
+        .min(cumulative.len() - 1)
+}
+
+// Weighted average of `source` around each texel's direction, with weights given by `lobe`
+// from the cosine between the two directions
+fn convolve(source: &Image, width: usize, height: usize, lobe: impl Fn(f64) -> f64) -> Image {
+    let texels: Vec<(Vec3, Rgb, f64)> = (0..source.height())
+        .flat_map(|y| (0..source.width()).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let u = (x as f64 + 0.5) / source.width() as f64;
+            let v = (y as f64 + 0.5) / source.height() as f64;
+            (from_uv(u, v), source.pixel(x, y), (v * PI).sin())
+        })
+        .collect();
+
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let u = (x as f64 + 0.5) / width as f64;
+            let v = (y as f64 + 0.5) / height as f64;
+            let center = from_uv(u, v);
+
+            let mut sum = Rgb::BLACK;
+            let mut weights = 0.0;
+            for (direction, radiance, solid_angle) in &texels {
+                let weight = lobe(center.dot(*direction)) * solid_angle;
+                sum = sum + *radiance * weight;
+                weights += weight;
+            }
+            pixels.push(sum * (1.0 / weights.max(1e-12)));
+        }
+    }
+
+    Image::new(width, height, pixels)
+}
@@ -0,0 +1,333 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, ErrorKind, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::Canvas;
+
+pub trait FrameWriter {
+    fn write_frame(&mut self, canvas: &Canvas) -> io::Result<()>;
+
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// binary PPM (P6)
+pub fn write_ppm(canvas: &Canvas, out: &mut impl Write) -> io::Result<()> {
+    write!(out, "P6\n{} {}\n255\n", canvas.width(), canvas.height())?;
+    for pixel in canvas.pixels() {
+        out.write_all(&[(pixel >> 16) as u8, (pixel >> 8) as u8, *pixel as u8])?;
+    }
+    Ok(())
+}
+
+// numbered frames: <directory>/frame_0000.ppm, frame_0001.ppm, ...
+pub struct ImageSequence {
+    directory: PathBuf,
+    frame: usize,
+}
+
+impl ImageSequence {
+    pub fn new(directory: impl Into<PathBuf>) -> io::Result<Self> {
+        let directory = directory.into();
+        fs::create_dir_all(&directory)?;
+
+        Ok(Self {
+            directory,
+            frame: 0,
+        })
+    }
+}
+
+impl FrameWriter for ImageSequence {
+    fn write_frame(&mut self, canvas: &Canvas) -> io::Result<()> {
+        let path = self.directory.join(format!("frame_{:04}.ppm", self.frame));
+        let mut out = BufWriter::new(File::create(path)?);
+        write_ppm(canvas, &mut out)?;
+        out.flush()?;
+
+        self.frame += 1;
+        Ok(())
+    }
+}
+
+// Uncompressed 24-bit RIFF AVI, readable by ffmpeg, VLC and most players. Plain RIFF
+// addresses at most 4 GB, so frames that would take the file past that are refused.
+pub struct AviWriter {
+    out: BufWriter<File>,
+    width: usize,
+    height: usize,
+    fps: u32,
+    frames: u32,
+    frame_size: u32,       // bytes per frame, rows padded to 4 bytes
+    bytes_per_second: u32, // frame_size * fps
+}
+
+const AVI_HEADER_SIZE: u32 = 224;
+
+impl AviWriter {
+    pub fn create(
+        path: impl AsRef<Path>,
+        width: usize,
+        height: usize,
+        fps: u32,
+    ) -> io::Result<Self> {
+        if fps == 0 {
+            return Err(avi_error("frame rate must be positive"));
+        }
+        if width == 0 || height == 0 {
+            return Err(avi_error("frame size must be positive"));
+        }
+        // the stream header's frame rectangle holds 16-bit coordinates
+        if u16::try_from(width).is_err() || u16::try_from(height).is_err() {
+            return Err(avi_error("frame size too large"));
+        }
+        let stride = (width * 3 + 3) & !3;
+        let frame_size = stride
+            .checked_mul(height)
+            .and_then(|size| u32::try_from(size).ok())
+            .ok_or_else(|| avi_error("frame size too large"))?;
+        let bytes_per_second = frame_size
+            .checked_mul(fps)
+            .ok_or_else(|| avi_error("frames too large for the frame rate"))?;
+
+        let mut writer = Self {
+            out: BufWriter::new(File::create(path)?),
+            width,
+            height,
+            fps,
+            frames: 0,
+            frame_size,
+            bytes_per_second,
+        };
+        if writer.sizes(1).is_none() {
+            return Err(avi_error("frame size too large"));
+        }
+        // placeholder, rewritten with the final counts by finish()
+        writer.write_header()?;
+
+        Ok(writer)
+    }
+
+    fn stride(&self) -> usize {
+        (self.width * 3 + 3) & !3
+    }
+
+    // sizes of the "movi" list and of the RIFF chunk holding `frames` frames and their
+    // index, None past what the 32-bit size fields can hold
+    fn sizes(&self, frames: u32) -> Option<(u32, u32)> {
+        let movi_size = self
+            .frame_size
+            .checked_add(8)?
+            .checked_mul(frames)?
+            .checked_add(4)?;
+        // the header up to "movi" and the index, whose entries take 16 bytes each
+        let riff_size = movi_size
+            .checked_add(AVI_HEADER_SIZE - 12 + 8)?
+            .checked_add(frames.checked_mul(16)?)?;
+        Some((movi_size, riff_size))
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        let frame_size = self.frame_size;
+        // write_frame only lets frames in while these fit
+        let (movi_size, riff_size) = self
+            .sizes(self.frames)
+            .ok_or_else(|| avi_error("video would pass the 4 GB RIFF limit"))?;
+        let (width, height) = (self.width as u32, self.height as u32);
+
+        let mut header = Vec::with_capacity(AVI_HEADER_SIZE as usize);
+        let mut chunk = |id: &[u8; 4], values: &[u32]| {
+            header.extend_from_slice(id);
+            for v in values {
+                header.extend_from_slice(&v.to_le_bytes());
+            }
+        };
+
+        chunk(b"RIFF", &[riff_size]);
+        chunk(b"AVI ", &[]);
+        chunk(b"LIST", &[192]);
+        chunk(b"hdrl", &[]);
+        #[rustfmt::skip]
+        chunk(b"avih", &[
+            56,
+            1_000_000 / self.fps, // microseconds per frame
+            self.bytes_per_second,
+            0,
+            0x10, // AVIF_HASINDEX
+            self.frames,
+            0,
+            1, // streams
+            frame_size,
+            width,
+            height,
+            0, 0, 0, 0,
+        ]);
+        chunk(b"LIST", &[116]);
+        chunk(b"strl", &[]);
+        chunk(b"strh", &[56]);
+        #[rustfmt::skip]
+        chunk(b"vids", &[
+            u32::from_le_bytes(*b"DIB "),
+            0,
+            0, // priority, language
+            0,
+            1, // scale
+            self.fps, // rate
+            0,
+            self.frames, // length
+            frame_size,
+            u32::MAX, // default quality
+            0,
+            0, // rcFrame left, top
+            width | height << 16, // rcFrame right, bottom
+        ]);
+        #[rustfmt::skip]
+        chunk(b"strf", &[
+            40,
+            40, // BITMAPINFOHEADER size
+            width,
+            height, // positive: rows are stored bottom-up
+            1 | 24 << 16, // planes, bits per pixel
+            0, // BI_RGB
+            frame_size,
+            0, 0, 0, 0,
+        ]);
+        chunk(b"LIST", &[movi_size]);
+        chunk(b"movi", &[]);
+
+        self.out.seek(SeekFrom::Start(0))?;
+        self.out.write_all(&header)
+    }
+}
+
+impl FrameWriter for AviWriter {
+    fn write_frame(&mut self, canvas: &Canvas) -> io::Result<()> {
+        if canvas.width() != self.width || canvas.height() != self.height {
+            return Err(avi_error("canvas size doesn't match the video"));
+        }
+        let frames = self
+            .frames
+            .checked_add(1)
+            .filter(|frames| self.sizes(*frames).is_some())
+            .ok_or_else(|| avi_error("video would pass the 4 GB RIFF limit"))?;
+
+        self.out.write_all(b"00db")?;
+        self.out.write_all(&self.frame_size.to_le_bytes())?;
+
+        let padding = self.stride() - self.width * 3;
+        for row in canvas.pixels().chunks(self.width).rev() {
+            for pixel in row {
+                self.out
+                    .write_all(&[*pixel as u8, (pixel >> 8) as u8, (pixel >> 16) as u8])?;
+            }
+            self.out.write_all(&[0; 3][..padding])?;
+        }
+
+        self.frames = frames;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let frame_size = self.frame_size;
+        // the index and every offset into "movi" are smaller than the checked sizes
+        self.sizes(self.frames)
+            .ok_or_else(|| avi_error("video would pass the 4 GB RIFF limit"))?;
+
+        self.out.write_all(b"idx1")?;
+        self.out.write_all(&(16 * self.frames).to_le_bytes())?;
+        for frame in 0..self.frames {
+            // offsets are relative to the "movi" fourcc
+            let offset = 4 + frame * (8 + frame_size);
+            self.out.write_all(b"00db")?;
+            for v in [0x10, offset, frame_size] {
+                self.out.write_all(&u32::to_le_bytes(v))?;
+            }
+        }
+
+        self.write_header()?;
+        self.out.flush()
+    }
+}
+
+fn avi_error(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidInput, format!("avi: {message}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("cgfs_{}_{name}.avi", std::process::id()))
+    }
+
+    fn u32_at(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn avi_sizes() {
+        let path = temp_path("sizes");
+        // 3 pixels make 9 bytes per row, padded to 12
+        let canvas = Canvas::headless(3, 2);
+        let mut writer = AviWriter::create(&path, 3, 2, 25).unwrap();
+        writer.write_frame(&canvas).unwrap();
+        writer.write_frame(&canvas).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+        let data = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let frame_size = 24;
+        assert_eq!(&data[0..4], b"RIFF");
+        assert_eq!(u32_at(&data, 4) as usize, data.len() - 8);
+
+        let movi = AVI_HEADER_SIZE as usize - 12;
+        assert_eq!(&data[movi..movi + 4], b"LIST");
+        assert_eq!(&data[movi + 8..movi + 12], b"movi");
+        let movi_size = u32_at(&data, movi + 4) as usize;
+        assert_eq!(movi_size, 4 + 2 * (8 + frame_size));
+
+        let idx1 = movi + 8 + movi_size;
+        assert_eq!(&data[idx1..idx1 + 4], b"idx1");
+        assert_eq!(u32_at(&data, idx1 + 4), 32);
+        assert_eq!(idx1 + 8 + 32, data.len());
+        for frame in 0..2 {
+            let entry = idx1 + 8 + 16 * frame;
+            let offset = u32_at(&data, entry + 8) as usize;
+            assert_eq!(offset, 4 + frame * (8 + frame_size));
+            assert_eq!(u32_at(&data, entry + 12) as usize, frame_size);
+            assert_eq!(&data[movi + 8 + offset..movi + 12 + offset], b"00db");
+        }
+    }
+
+    #[test]
+    fn avi_rejects_bad_sizes() {
+        let path = temp_path("bad_sizes");
+        for (width, height, fps) in [(0, 0, 30), (4, 0, 30), (4, 4, 0), (70000, 1, 30)] {
+            let error = AviWriter::create(&path, width, height, fps).err().unwrap();
+            assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        }
+        // 30000x30000 fits a frame but not a second's worth of them
+        let error = AviWriter::create(&path, 30000, 30000, 30).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+
+        let mut writer = AviWriter::create(&path, 4, 4, 30).unwrap();
+        let error = writer.write_frame(&Canvas::headless(4, 5)).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        drop(writer);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn avi_riff_limit() {
+        let path = temp_path("riff_limit");
+        // 48 MB frames, of which 89 fit in 4 GB
+        let writer = AviWriter::create(&path, 4000, 4000, 1).unwrap();
+        assert!(writer.sizes(89).is_some());
+        assert!(writer.sizes(90).is_none());
+        drop(writer);
+        fs::remove_file(&path).unwrap();
+    }
+}
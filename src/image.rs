@@ -0,0 +1,342 @@
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+use crate::Rgb;
+
+// Linear floating point image, row-major from the top-left corner. Empty images are valid
+// and sample as black.
+#[derive(Clone)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgb>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, pixels: Vec<Rgb>) -> Self {
+        assert_eq!(
+            pixels.len(),
+            width * height,
+            "pixel count doesn't match the size"
+        );
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    // Radiance HDR for files ending in .hdr, PPM otherwise
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let data = fs::read(path)?;
+        if path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("hdr"))
+        {
+            Self::parse_hdr(&data)
+        } else {
+            Self::parse_ppm(&data)
+        }
+    }
+
+    // binary (P6) or plain (P3) PPM with up to 8 bits per channel
+    pub fn parse_ppm(data: &[u8]) -> io::Result<Self> {
+        let magic = data.get(..2);
+        if magic != Some(b"P6") && magic != Some(b"P3") {
+            return Err(ppm_error("not a P3 or P6 file"));
+        }
+
+        let mut fields = Fields { data, position: 2 };
+        let width = fields.number()?;
+        let height = fields.number()?;
+        let max = fields.number()?;
+        if max == 0 || max > 255 {
+            return Err(ppm_error("only 8-bit images are supported"));
+        }
+
+        let count = width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(3))
+            .filter(|n| *n > 0)
+            .ok_or_else(|| ppm_error("malformed size"))?;
+        let samples: Vec<usize> = if magic == Some(b"P6") {
+            // a single whitespace byte separates the header from the samples
+            let start = fields.position + 1;
+            start
+                .checked_add(count)
+                .and_then(|end| data.get(start..end))
+                .ok_or_else(|| ppm_error("truncated pixel data"))?
+                .iter()
+                .map(|b| *b as usize)
+                .collect()
+        } else {
+            (0..count)
+                .map(|_| fields.number())
+                .collect::<io::Result<_>>()?
+        };
+
+        let scale = 1.0 / max as f64;
+        let pixels = samples
+            .chunks(3)
+            .map(|c| Rgb(c[0] as f64, c[1] as f64, c[2] as f64) * scale)
+            .collect();
+
+        Ok(Self::new(width, height, pixels))
+    }
+
+    // Radiance RGBE with flat or run-length encoded scanlines, stored top to bottom
+    pub fn parse_hdr(data: &[u8]) -> io::Result<Self> {
+        if !data.starts_with(b"#?") {
+            return Err(hdr_error("missing #? signature"));
+        }
+
+        // header lines up to a blank one, then the resolution line
+        let mut position = 0;
+        let mut line = || {
+            let start = position;
+            let end = data[start..]
+                .iter()
+                .position(|b| *b == b'\n')
+                .map(|n| start + n)
+                .ok_or_else(|| hdr_error("truncated header"))?;
+            position = end + 1;
+            Ok::<_, io::Error>(String::from_utf8_lossy(&data[start..end]).into_owned())
+        };
+        loop {
+            let line = line()?;
+            if line.is_empty() {
+                break;
+            }
+            if let Some(format) = line.strip_prefix("FORMAT=") {
+                if format != "32-bit_rle_rgbe" {
+                    return Err(hdr_error("only RGBE pixels are supported"));
+                }
+            }
+        }
+        let resolution = line()?;
+        let (height, width) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+            ["-Y", height, "+X", width] => (height.parse().ok(), width.parse().ok()),
+            _ => return Err(hdr_error("only -Y +X orientation is supported")),
+        };
+        let (height, width): (usize, usize) = height
+            .zip(width)
+            .ok_or_else(|| hdr_error("malformed resolution"))?;
+        if width == 0 || height == 0 || width.checked_mul(height).is_none() {
+            return Err(hdr_error("malformed resolution"));
+        }
+
+        // Every scanline takes at least 4 bytes, and one too wide to run-length encode takes
+        // 4 per pixel, so sizes the data can't hold are refused before anything is allocated.
+        // Pixels still only grow as scanlines decode.
+        let remaining = data.len() - position;
+        if height > remaining / 4 || (width >= 0x8000 && width > remaining / 4) {
+            return Err(hdr_error("truncated pixel data"));
+        }
+
+        let mut bytes = data[position..].iter().copied();
+        let mut pixels = Vec::new();
+        let mut scanline = vec![[0u8; 4]; width];
+        for _ in 0..height {
+            read_scanline(&mut bytes, &mut scanline)?;
+            pixels.extend(scanline.iter().map(|&rgbe| from_rgbe(rgbe)));
+        }
+
+        Ok(Self::new(width, height, pixels))
+    }
+
+    // box filtered copy, for images at least as large as the new size
+    pub fn downsampled(&self, width: usize, height: usize) -> Self {
+        let (width, height) = (width.min(self.width), height.min(self.height));
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let rows = y * self.height / height..(y + 1) * self.height / height;
+            for x in 0..width {
+                let columns = x * self.width / width..(x + 1) * self.width / width;
+                let mut sum = Rgb::BLACK;
+                for sy in rows.clone() {
+                    for sx in columns.clone() {
+                        sum = sum + self.pixel(sx, sy);
+                    }
+                }
+                pixels.push(sum * (1.0 / (rows.len() * columns.len()) as f64));
+            }
+        }
+
+        Self::new(width, height, pixels)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pixels.is_empty()
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Rgb {
+        self.pixels[y * self.width + x]
+    }
+
+    // Bilinear filtering, with u and v from 0 to 1 across the image starting at its top-left
+    // corner. Wraps around horizontally and clamps vertically.
+    pub fn sample(&self, u: f64, v: f64) -> Rgb {
+        if self.is_empty() {
+            return Rgb::BLACK;
+        }
+        let x = u * self.width as f64 - 0.5;
+        let y = (v * self.height as f64 - 0.5).clamp(0.0, (self.height - 1) as f64);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+
+        let column = |x: f64| (x as i64).rem_euclid(self.width as i64) as usize;
+        let (x0, x1) = (column(x0), column(x0 + 1.0));
+        let (y0, y1) = (y0 as usize, (y0 as usize + 1).min(self.height - 1));
+
+        let top = self.pixel(x0, y0) * (1.0 - fx) + self.pixel(x1, y0) * fx;
+        let bottom = self.pixel(x0, y1) * (1.0 - fx) + self.pixel(x1, y1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+fn ppm_error(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, format!("ppm: {message}"))
+}
+
+fn hdr_error(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, format!("hdr: {message}"))
+}
+
+fn from_rgbe([r, g, b, e]: [u8; 4]) -> Rgb {
+    if e == 0 {
+        return Rgb::BLACK;
+    }
+    // the exponent scales mantissas that are 8-bit fractions
+    let scale = 2f64.powi(e as i32 - 136);
+    Rgb(r as f64, g as f64, b as f64) * scale
+}
+
+// New-style scanlines hold each channel run-length encoded in turn, anything else is flat
+fn read_scanline(bytes: &mut impl Iterator<Item = u8>, scanline: &mut [[u8; 4]]) -> io::Result<()> {
+    let mut next = || {
+        bytes
+            .next()
+            .ok_or_else(|| hdr_error("truncated pixel data"))
+    };
+
+    let first = [next()?, next()?, next()?, next()?];
+    let width = scanline.len();
+    let encoded = (8..0x8000).contains(&width) && first[0] == 2 && first[1] == 2 && first[2] < 128;
+    if !encoded {
+        scanline[0] = first;
+        for pixel in &mut scanline[1..] {
+            *pixel = [next()?, next()?, next()?, next()?];
+        }
+        return Ok(());
+    }
+
+    if ((first[2] as usize) << 8 | first[3] as usize) != width {
+        return Err(hdr_error("scanline length doesn't match the width"));
+    }
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = next()? as usize;
+            let (run, count) = if count > 128 {
+                (true, count - 128)
+            } else {
+                (false, count)
+            };
+            if count == 0 || x + count > width {
+                return Err(hdr_error("bad run length"));
+            }
+            if run {
+                let value = next()?;
+                for pixel in &mut scanline[x..x + count] {
+                    pixel[channel] = value;
+                }
+            } else {
+                for pixel in &mut scanline[x..x + count] {
+                    pixel[channel] = next()?;
+                }
+            }
+            x += count;
+        }
+    }
+    Ok(())
+}
+
+// whitespace separated header fields, with comments up to the end of the line
+struct Fields<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl Fields<'_> {
+    fn number(&mut self) -> io::Result<usize> {
+        loop {
+            match self.data.get(self.position) {
+                Some(b'#') => {
+                    while self.data.get(self.position).is_some_and(|b| *b != b'\n') {
+                        self.position += 1;
+                    }
+                }
+                Some(b) if b.is_ascii_whitespace() => self.position += 1,
+                Some(_) => break,
+                None => return Err(ppm_error("truncated file")),
+            }
+        }
+
+        let start = self.position;
+        while self
+            .data
+            .get(self.position)
+            .is_some_and(|b| b.is_ascii_digit())
+        {
+            self.position += 1;
+        }
+        std::str::from_utf8(&self.data[start..self.position])
+            .ok()
+            .and_then(|n| n.parse().ok())
+            .ok_or_else(|| ppm_error("malformed number"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hdr(resolution: &str, pixels: &[u8]) -> io::Result<Image> {
+        let mut data = format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n{resolution}\n").into_bytes();
+        data.extend_from_slice(pixels);
+        Image::parse_hdr(&data)
+    }
+
+    #[test]
+    fn parses_flat_hdr() {
+        let image = hdr("-Y 1 +X 2", &[128, 64, 0, 129, 0, 0, 0, 0]).unwrap();
+        assert_eq!((image.width(), image.height()), (2, 1));
+        assert_eq!(image.pixel(0, 0).0, 1.0);
+        assert_eq!(image.pixel(1, 0).0, 0.0);
+    }
+
+    #[test]
+    fn rejects_bad_hdr_sizes() {
+        for resolution in [
+            "-Y 0 +X 4",
+            "-Y 1 +X 0",
+            "-Y 18446744073709551615 +X 2",
+            "-Y 3000000000 +X 3000000000",
+            "-Y 1 +X 3000000000",
+            "-Y 1000 +X 1",
+        ] {
+            let error = hdr(resolution, &[0; 16]).err().unwrap();
+            assert_eq!(error.kind(), ErrorKind::InvalidData, "{resolution}");
+        }
+    }
+}
@@ -0,0 +1,558 @@
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    MoveFront,
+    MoveBack,
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    TurnLeft,
+    TurnRight,
+    LookUp,
+    LookDown,
+    ZoomIn,
+    ZoomOut,
+    Faster,
+    Slower,
+    NextMode,
+    NextDrawMode,
+}
+
+impl Action {
+    fn name(self) -> &'static str {
+        Self::NAMES
+            .iter()
+            .find(|(_, action)| *action == self)
+            .map(|(name, _)| *name)
+            .unwrap()
+    }
+
+    const NAMES: [(&'static str, Action); 16] = [
+        ("move_front", Action::MoveFront),
+        ("move_back", Action::MoveBack),
+        ("move_left", Action::MoveLeft),
+        ("move_right", Action::MoveRight),
+        ("move_up", Action::MoveUp),
+        ("move_down", Action::MoveDown),
+        ("turn_left", Action::TurnLeft),
+        ("turn_right", Action::TurnRight),
+        ("look_up", Action::LookUp),
+        ("look_down", Action::LookDown),
+        ("zoom_in", Action::ZoomIn),
+        ("zoom_out", Action::ZoomOut),
+        ("faster", Action::Faster),
+        ("slower", Action::Slower),
+        ("next_mode", Action::NextMode),
+        ("next_draw_mode", Action::NextDrawMode),
+    ];
+
+    // fires once per key press instead of every frame the key is held
+    fn is_toggle(self) -> bool {
+        self == Action::NextMode || self == Action::NextDrawMode
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        Self::NAMES
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, action)| *action)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Modifier {
+    Shift,
+    Ctrl,
+    Alt,
+}
+
+impl Modifier {
+    fn is_down(self, window: &Window) -> bool {
+        let (left, right) = match self {
+            Modifier::Shift => (Key::LeftShift, Key::RightShift),
+            Modifier::Ctrl => (Key::LeftCtrl, Key::RightCtrl),
+            Modifier::Alt => (Key::LeftAlt, Key::RightAlt),
+        };
+        window.is_key_down(left) || window.is_key_down(right)
+    }
+}
+
+// what the mouse wheel does
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ScrollMode {
+    Speed,
+    Fov,
+}
+
+#[derive(Clone, Copy)]
+struct Binding {
+    modifier: Option<Modifier>,
+    key: Key,
+    action: Action,
+}
+
+// Maps keys and the mouse to actions. Config files contain `binding = value` lines:
+//
+//   Shift+A = turn_left
+//   Q = none
+//   mouse_sensitivity = 0.2
+//   scroll = fov
+//
+// Key names are minifb's (`A`, `Key1`, `Up`, `PageDown`, ...). Lines override the
+// defaults, `none` removes a binding and `#` starts a comment.
+#[derive(Clone)]
+pub struct Bindings {
+    keys: Vec<Binding>,
+    pub mouse_sensitivity: f64, // degrees per pixel dragged
+    pub invert_mouse: bool,
+    pub scroll: ScrollMode,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let keys = [
+            (None, Key::W, Action::MoveFront),
+            (None, Key::S, Action::MoveBack),
+            (None, Key::A, Action::MoveLeft),
+            (None, Key::D, Action::MoveRight),
+            (None, Key::Up, Action::MoveUp),
+            (None, Key::Down, Action::MoveDown),
+            (Some(Modifier::Shift), Key::A, Action::TurnLeft),
+            (Some(Modifier::Shift), Key::D, Action::TurnRight),
+            (None, Key::Left, Action::TurnLeft),
+            (None, Key::Right, Action::TurnRight),
+            (Some(Modifier::Shift), Key::Up, Action::LookUp),
+            (Some(Modifier::Shift), Key::Down, Action::LookDown),
+            (None, Key::Equal, Action::ZoomIn),
+            (None, Key::Minus, Action::ZoomOut),
+            (None, Key::M, Action::NextMode),
+            (None, Key::N, Action::NextDrawMode),
+        ];
+
+        Self {
+            keys: keys
+                .into_iter()
+                .map(|(modifier, key, action)| Binding {
+                    modifier,
+                    key,
+                    action,
+                })
+                .collect(),
+            mouse_sensitivity: 0.2,
+            invert_mouse: false,
+            scroll: ScrollMode::Speed,
+        }
+    }
+}
+
+impl Bindings {
+    pub fn parse(config: &str) -> io::Result<Self> {
+        let mut bindings = Self::default();
+
+        for (n, line) in config.lines().enumerate() {
+            let error = |message: &str| {
+                io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("bindings line {}: {message}", n + 1),
+                )
+            };
+
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (name, value) = line.split_once('=').ok_or_else(|| error("expected `=`"))?;
+            let (name, value) = (name.trim(), value.trim());
+
+            match name {
+                "mouse_sensitivity" => {
+                    bindings.mouse_sensitivity =
+                        value.parse().map_err(|_| error("invalid sensitivity"))?;
+                }
+                "invert_mouse" => {
+                    bindings.invert_mouse = value.parse().map_err(|_| error("expected bool"))?;
+                }
+                "scroll" => {
+                    bindings.scroll = match value {
+                        "speed" => ScrollMode::Speed,
+                        "fov" => ScrollMode::Fov,
+                        _ => return Err(error("expected `speed` or `fov`")),
+                    };
+                }
+                _ => {
+                    let (modifier, key) = match name.split_once('+') {
+                        Some((modifier, key)) => {
+                            let modifier = match modifier.trim() {
+                                "Shift" => Modifier::Shift,
+                                "Ctrl" => Modifier::Ctrl,
+                                "Alt" => Modifier::Alt,
+                                _ => return Err(error("unknown modifier")),
+                            };
+                            (Some(modifier), key.trim())
+                        }
+                        None => (None, name),
+                    };
+                    let key = parse_key(key).ok_or_else(|| error("unknown key"))?;
+
+                    bindings
+                        .keys
+                        .retain(|b| !(b.key == key && b.modifier == modifier));
+                    if value != "none" {
+                        let action = Action::parse(value).ok_or_else(|| error("unknown action"))?;
+                        bindings.keys.push(Binding {
+                            modifier,
+                            key,
+                            action,
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(bindings)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    // the defaults when the file doesn't exist
+    pub fn load_or_default(path: impl AsRef<Path>) -> io::Result<Self> {
+        match Self::load(path) {
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            result => result,
+        }
+    }
+
+    fn actions(&self, window: &Window) -> Vec<Action> {
+        let active: Vec<&Binding> = self
+            .keys
+            .iter()
+            .filter(|b| {
+                if b.action.is_toggle() {
+                    window.is_key_pressed(b.key, KeyRepeat::No)
+                } else {
+                    window.is_key_down(b.key)
+                }
+            })
+            .filter(|b| b.modifier.is_none_or(|m| m.is_down(window)))
+            .collect();
+
+        // Shift+A shadows plain A while shift is held
+        active
+            .iter()
+            .filter(|b| {
+                b.modifier.is_some()
+                    || !active
+                        .iter()
+                        .any(|other| other.key == b.key && other.modifier.is_some())
+            })
+            .map(|b| b.action)
+            .collect()
+    }
+}
+
+// everything the renderer needs to know about one frame of user input
+#[derive(Clone, Default)]
+pub struct FrameInput {
+    pub elapsed: f64, // seconds since the previous frame
+    pub actions: Vec<Action>,
+    pub look: (f64, f64), // yaw and pitch in degrees from mouse dragging
+    pub scroll: f64,
+    pub focus: Option<(i32, i32)>, // canvas point right-clicked to focus on
+}
+
+// A session's input, frame by frame. Replaying it against the same starting scene, on a
+// canvas of the recorded size, reproduces every frame exactly. Saved as text, a header
+// followed by one frame per line:
+//
+//   <time> <elapsed> <yaw> <pitch> <scroll> [action,action,...] [@<x>,<y>]
+//
+// where the last field is a focus click.
+pub struct Recording {
+    pub size: (usize, usize), // canvas width and height, as focus clicks are canvas points
+    pub samples: u32,         // passes accumulated for each frame
+    pub movement_speed: f64,
+    pub rotation_speed: f64,
+    pub scroll: ScrollMode,
+    pub frames: Vec<FrameInput>,
+}
+
+impl Recording {
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let scroll = match self.scroll {
+            ScrollMode::Speed => "speed",
+            ScrollMode::Fov => "fov",
+        };
+        let (width, height) = self.size;
+        let mut out = format!(
+            "size {width} {height}\nsamples {}\nspeed {} {}\nscroll {scroll}\n",
+            self.samples, self.movement_speed, self.rotation_speed
+        );
+
+        let mut time = 0.0;
+        for frame in &self.frames {
+            time += frame.elapsed;
+            let actions: Vec<&str> = frame.actions.iter().map(|a| a.name()).collect();
+            out += &format!(
+                "{time} {} {} {} {} {}",
+                frame.elapsed,
+                frame.look.0,
+                frame.look.1,
+                frame.scroll,
+                actions.join(",")
+            );
+            if let Some((x, y)) = frame.focus {
+                out += &format!(" @{x},{y}");
+            }
+            out += "\n";
+        }
+
+        fs::write(path, out)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut recording = Self {
+            size: (0, 0),
+            samples: 0,
+            movement_speed: 0.0,
+            rotation_speed: 0.0,
+            scroll: ScrollMode::Speed,
+            frames: Vec::new(),
+        };
+
+        for (n, line) in fs::read_to_string(path)?.lines().enumerate() {
+            let error = || {
+                io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("recording line {}: malformed", n + 1),
+                )
+            };
+            let number = |field: Option<&str>| -> io::Result<f64> {
+                field.and_then(|f| f.parse().ok()).ok_or_else(error)
+            };
+            let count = |field: Option<&str>| -> io::Result<usize> {
+                field
+                    .and_then(|f| f.parse().ok())
+                    .filter(|n| *n > 0)
+                    .ok_or_else(error)
+            };
+
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("size") => recording.size = (count(fields.next())?, count(fields.next())?),
+                Some("samples") => {
+                    recording.samples =
+                        u32::try_from(count(fields.next())?).map_err(|_| error())?;
+                }
+                Some("speed") => {
+                    recording.movement_speed = number(fields.next())?;
+                    recording.rotation_speed = number(fields.next())?;
+                }
+                Some("scroll") => {
+                    recording.scroll = match fields.next() {
+                        Some("speed") => ScrollMode::Speed,
+                        Some("fov") => ScrollMode::Fov,
+                        _ => return Err(error()),
+                    };
+                }
+                Some(_) => {
+                    let mut frame = FrameInput {
+                        elapsed: number(fields.next())?,
+                        look: (number(fields.next())?, number(fields.next())?),
+                        scroll: number(fields.next())?,
+                        ..FrameInput::default()
+                    };
+                    for field in fields {
+                        if let Some(point) = field.strip_prefix('@') {
+                            let (x, y) = point.split_once(',').ok_or_else(error)?;
+                            let (x, y) = (x.parse().ok(), y.parse().ok());
+                            frame.focus = Some(x.zip(y).ok_or_else(error)?);
+                        } else {
+                            frame.actions = field
+                                .split(',')
+                                .map(|name| Action::parse(name).ok_or_else(error))
+                                .collect::<io::Result<_>>()?;
+                        }
+                    }
+                    recording.frames.push(frame);
+                }
+                None => {}
+            }
+        }
+
+        if recording.size == (0, 0) || recording.samples == 0 {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "recording: missing size or samples",
+            ));
+        }
+        Ok(recording)
+    }
+}
+
+// polls a window, tracking the mouse between frames
+pub(crate) struct InputState {
+    pub bindings: Bindings,
+    last_mouse: Option<(f32, f32)>,
+    right_down: bool,
+}
+
+impl InputState {
+    pub fn new(bindings: Bindings) -> Self {
+        Self {
+            bindings,
+            last_mouse: None,
+            right_down: false,
+        }
+    }
+
+    pub fn poll(&mut self, window: &Window, elapsed: f64) -> FrameInput {
+        let mouse = window.get_mouse_pos(MouseMode::Pass);
+        let mut look = (0.0, 0.0);
+        if window.get_mouse_down(MouseButton::Left) {
+            if let (Some((x0, y0)), Some((x1, y1))) = (self.last_mouse, mouse) {
+                let sensitivity = self.bindings.mouse_sensitivity;
+                let pitch = (y0 - y1) as f64 * sensitivity;
+                look = (
+                    (x1 - x0) as f64 * sensitivity,
+                    if self.bindings.invert_mouse {
+                        -pitch
+                    } else {
+                        pitch
+                    },
+                );
+            }
+        }
+        self.last_mouse = mouse;
+
+        // focus once per right click, in canvas coordinates
+        let right_down = window.get_mouse_down(MouseButton::Right);
+        let mut focus = None;
+        if right_down && !self.right_down {
+            if let Some((x, y)) = mouse {
+                let (width, height) = window.get_size();
+                focus = Some((
+                    x as i32 - width as i32 / 2,
+                    height as i32 / 2 - y as i32 - 1,
+                ));
+            }
+        }
+        self.right_down = right_down;
+
+        FrameInput {
+            elapsed,
+            actions: self.bindings.actions(window),
+            look,
+            scroll: window.get_scroll_wheel().map_or(0.0, |(_, y)| y as f64),
+            focus,
+        }
+    }
+}
+
+fn parse_key(name: &str) -> Option<Key> {
+    use Key::*;
+    #[rustfmt::skip]
+    const KEYS: [Key; 106] = [
+        Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9,
+        A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+        F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, F13, F14, F15,
+        Down, Left, Right, Up, Apostrophe, Backquote, Backslash, Comma, Equal, LeftBracket,
+        Minus, Period, RightBracket, Semicolon, Slash, Backspace, Delete, End, Enter, Escape,
+        Home, Insert, Menu, PageDown, PageUp, Pause, Space, Tab, NumLock, CapsLock, ScrollLock,
+        LeftShift, RightShift, LeftCtrl, RightCtrl,
+        NumPad0, NumPad1, NumPad2, NumPad3, NumPad4, NumPad5, NumPad6, NumPad7, NumPad8,
+        NumPad9, NumPadDot, NumPadSlash, NumPadAsterisk, NumPadMinus, NumPadPlus, NumPadEnter,
+        LeftAlt, RightAlt, LeftSuper, RightSuper,
+    ];
+
+    KEYS.into_iter().find(|key| format!("{key:?}") == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Canvas, Rasterizer};
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("cgfs_{}_{name}.rec", std::process::id()))
+    }
+
+    fn recording() -> Recording {
+        Recording {
+            size: (40, 30),
+            samples: 16,
+            movement_speed: 2.5,
+            rotation_speed: 45.0,
+            scroll: ScrollMode::Fov,
+            frames: vec![
+                FrameInput {
+                    elapsed: 0.25,
+                    actions: vec![Action::MoveFront, Action::TurnLeft],
+                    look: (1.5, -2.0),
+                    scroll: 1.0,
+                    focus: None,
+                },
+                FrameInput {
+                    elapsed: 0.5,
+                    focus: Some((-3, 7)),
+                    ..FrameInput::default()
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn recording_round_trip() {
+        let path = temp_path("round_trip");
+        let saved = recording();
+        saved.save(&path).unwrap();
+        let loaded = Recording::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!((loaded.size, loaded.samples), (saved.size, saved.samples));
+        assert_eq!(loaded.movement_speed, saved.movement_speed);
+        assert_eq!(loaded.rotation_speed, saved.rotation_speed);
+        assert!(loaded.scroll == saved.scroll);
+        assert_eq!(loaded.frames.len(), saved.frames.len());
+        for (loaded, saved) in loaded.frames.iter().zip(&saved.frames) {
+            assert_eq!(loaded.elapsed, saved.elapsed);
+            assert!(loaded.actions == saved.actions);
+            assert_eq!(loaded.look, saved.look);
+            assert_eq!(loaded.scroll, saved.scroll);
+            assert_eq!(loaded.focus, saved.focus);
+        }
+    }
+
+    #[test]
+    fn rejects_recordings_without_a_size() {
+        let path = temp_path("no_size");
+        fs::write(
+            &path,
+            "speed 3 90\nscroll speed\n0.1 0.1 0 0 0 move_front\n",
+        )
+        .unwrap();
+        let error = Recording::load(&path).err().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn replay_needs_the_recorded_size() {
+        let mut rasterizer = Rasterizer::default_scene();
+        let error = Canvas::headless(30, 40)
+            .replay(&mut rasterizer, &recording(), None)
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+
+        let mut canvas = Canvas::headless(40, 30);
+        canvas.replay(&mut rasterizer, &recording(), None).unwrap();
+    }
+}
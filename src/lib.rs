@@ -1,14 +1,41 @@
+mod background;
 mod canvas;
 mod color;
+mod draw;
+mod environment;
+mod export;
+mod image;
+mod input;
+mod light;
+mod material;
 mod matrix;
+mod options;
+mod primitive;
+mod random;
 mod rasterizer;
 mod raytracer;
+mod sdf;
+mod texture;
 mod vec3;
 
-use color::Color;
 use matrix::Matrix;
-use vec3::Vec3;
 
+pub use background::Background;
 pub use canvas::{Canvas, Renderer};
-pub use rasterizer::Rasterizer;
-pub use raytracer::Raytracer;
+pub use color::{Color, Rgb};
+pub use environment::EnvironmentMap;
+pub use export::{write_ppm, AviWriter, FrameWriter, ImageSequence};
+pub use image::Image;
+pub use input::{Action, Bindings, FrameInput, Recording, ScrollMode};
+pub use light::Attenuation;
+pub use material::{BumpMap, Material, MetallicRoughness};
+pub use options::Options;
+pub use primitive::{
+    AxisBox, Bounds, Cone, Csg, Cylinder, Disc, Hit, Moving, Operation, OrientedBox, Plane,
+    Primitive, Span, Sphere, Transform,
+};
+pub use rasterizer::{DrawMode, Light as RasterizerLight, Model, Rasterizer, ShadingModel};
+pub use raytracer::{Integrator, Light, Object, Raytracer};
+pub use sdf::{DistanceField, Metaballs, RoundBox, Sdf, SmoothUnion, Torus};
+pub use texture::{Pattern, Texture};
+pub use vec3::Vec3;
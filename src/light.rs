@@ -0,0 +1,48 @@
+use crate::Vec3;
+
+// How a positional light's intensity falls off with distance
+#[derive(Clone, Copy)]
+pub enum Attenuation {
+    // the same at any distance, as in the book
+    None,
+    // 1 / (constant + linear * d + quadratic * d^2)
+    Polynomial {
+        constant: f64,
+        linear: f64,
+        quadratic: f64,
+    },
+    // physically based 1 / d^2, smoothly windowed to reach zero at `range`
+    InverseSquare {
+        range: f64,
+    },
+}
+
+impl Attenuation {
+    pub fn factor(&self, distance: f64) -> f64 {
+        match *self {
+            Attenuation::None => 1.0,
+            Attenuation::Polynomial {
+                constant,
+                linear,
+                quadratic,
+            } => 1.0 / (constant + linear * distance + quadratic * distance * distance).max(1e-6),
+            Attenuation::InverseSquare { range } => {
+                let window = (1.0 - (distance / range).powi(4)).clamp(0.0, 1.0);
+                window * window / (distance * distance).max(1e-4)
+            }
+        }
+    }
+}
+
+// 1 inside the inner cone around `axis`, 0 outside the outer one and smooth in between
+pub(crate) fn spot_falloff(axis: Vec3, to_point: Vec3, inner: f64, outer: f64) -> f64 {
+    let cos = axis.dot(to_point) / (axis.length() * to_point.length());
+    let cos_inner = inner.to_radians().cos();
+    let cos_outer = outer.to_radians().cos();
+    if cos_inner <= cos_outer {
+        return if cos >= cos_outer { 1.0 } else { 0.0 };
+    }
+
+    let t = ((cos - cos_outer) / (cos_inner - cos_outer)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
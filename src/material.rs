@@ -0,0 +1,337 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use crate::random::{orthonormal_basis, Rng};
+use crate::{Color, Image, Pattern, Rgb, Texture, Vec3};
+
+// Surface properties shared by both renderers. Objects and triangles refer to a material by
+// its index in the scene's material list. The rasterizer has no secondary rays, so it
+// ignores reflection and transparency.
+//
+// Textures and masks are evaluated at world space points. Masks scale a property by the
+// pattern's value, so 0 turns it off. Normal and bump maps are looked up at the surface's
+// texture coordinates instead, and tilt the shading normal without changing the geometry.
+#[derive(Clone)]
+pub struct Material {
+    pub albedo: Color,
+    pub specular: f64, // Phong exponent, -1 for no highlights
+    pub reflective: f64,
+    pub transparency: f64, // fraction of light passing straight through
+    pub emission: Rgb,
+    pub pbr: Option<MetallicRoughness>, // replaces the Phong specular term when set
+    pub albedo_texture: Option<Texture>, // replaces the albedo when set
+    pub specular_mask: Option<Pattern>, // highlight strength, or glossiness with PBR
+    pub reflective_mask: Option<Pattern>,
+    // tangent space normals encoded as colors, x and y growing with u and up the image
+    pub normal_map: Option<Arc<Image>>,
+    pub bump_map: Option<BumpMap>,
+}
+
+// heights from the red channel, with `strength` scaling the slopes across one texture width
+#[derive(Clone)]
+pub struct BumpMap {
+    pub heights: Arc<Image>,
+    pub strength: f64,
+}
+
+impl Material {
+    // matte, opaque and not emissive
+    pub fn new(albedo: Color) -> Self {
+        Self {
+            albedo,
+            specular: -1.0,
+            reflective: 0.0,
+            transparency: 0.0,
+            emission: Rgb::BLACK,
+            pbr: None,
+            albedo_texture: None,
+            specular_mask: None,
+            reflective_mask: None,
+            normal_map: None,
+            bump_map: None,
+        }
+    }
+
+    pub fn with_specular(mut self, specular: f64) -> Self {
+        self.specular = specular;
+        self
+    }
+
+    pub fn with_reflective(mut self, reflective: f64) -> Self {
+        self.reflective = reflective;
+        self
+    }
+
+    pub fn with_transparency(mut self, transparency: f64) -> Self {
+        self.transparency = transparency;
+        self
+    }
+
+    pub fn with_emission(mut self, emission: Rgb) -> Self {
+        self.emission = emission;
+        self
+    }
+
+    pub fn with_metallic_roughness(mut self, metallic: f64, roughness: f64) -> Self {
+        self.pbr = Some(MetallicRoughness {
+            metallic: metallic.clamp(0.0, 1.0),
+            roughness: roughness.clamp(0.0, 1.0),
+        });
+        self
+    }
+
+    pub fn with_albedo_texture(mut self, texture: Texture) -> Self {
+        self.albedo_texture = Some(texture);
+        self
+    }
+
+    pub fn with_specular_mask(mut self, mask: Pattern) -> Self {
+        self.specular_mask = Some(mask);
+        self
+    }
+
+    pub fn with_reflective_mask(mut self, mask: Pattern) -> Self {
+        self.reflective_mask = Some(mask);
+        self
+    }
+
+    pub fn with_normal_map(mut self, normals: Arc<Image>) -> Self {
+        self.normal_map = Some(normals);
+        self
+    }
+
+    pub fn with_bump_map(mut self, heights: Arc<Image>, strength: f64) -> Self {
+        self.bump_map = Some(BumpMap { heights, strength });
+        self
+    }
+
+    pub fn albedo_at(&self, point: Vec3) -> Color {
+        self.albedo_texture
+            .map_or(self.albedo, |texture| texture.color(point))
+    }
+
+    pub fn reflective_at(&self, point: Vec3) -> f64 {
+        self.reflective * self.reflective_mask.map_or(1.0, |mask| mask.value(point))
+    }
+
+    // scale of the Phong highlight
+    pub fn specular_strength_at(&self, point: Vec3) -> f64 {
+        self.specular_mask.map_or(1.0, |mask| mask.value(point))
+    }
+
+    // Phong response to a light in the Whitted convention: the diffuse cosine plus the
+    // highlight, untinted by the albedo
+    pub(crate) fn phong(&self, point: Vec3, normal: Vec3, to_light: Vec3, view: Vec3) -> f64 {
+        let mut i = 0.0;
+
+        // diffuse
+        let normal_dot_direction = normal.dot(to_light);
+        if normal_dot_direction > 0.0 {
+            i += normal_dot_direction / (normal.length() * to_light.length());
+        }
+
+        //specular
+        if self.specular != -1.0 {
+            let reflection = to_light.reflect(normal);
+            let reflection_dot_view = reflection.dot(view);
+            if reflection_dot_view > 0.0 {
+                i += (reflection_dot_view / (reflection.length() * view.length()))
+                    .powf(self.specular)
+                    * self.specular_strength_at(point);
+            }
+        }
+        i
+    }
+
+    // Light reflected towards `view` per unit of light arriving from `to_light`, cosine
+    // included, for Phong and PBR surfaces alike. Nothing is reflected from below the surface.
+    pub(crate) fn reflect(&self, point: Vec3, normal: Vec3, to_light: Vec3, view: Vec3) -> Rgb {
+        let albedo = Rgb::from(self.albedo_at(point));
+        match self.pbr_at(point) {
+            Some(pbr) => pbr.reflect(albedo, normal, to_light, view),
+            None if normal.dot(to_light) > 0.0 => {
+                albedo * self.phong(point, normal, to_light, view)
+            }
+            None => Rgb::BLACK,
+        }
+    }
+
+    // A direction to gather light from for a surface seen from `view`, and the light reflected
+    // towards `view` per unit of radiance arriving from there, divided by the probability
+    // density of having picked it. PBR surfaces favour their specular lobe.
+    pub(crate) fn sample_reflection(
+        &self,
+        point: Vec3,
+        normal: Vec3,
+        view: Vec3,
+        rng: &mut Rng,
+    ) -> (Vec3, Rgb) {
+        let (n, v) = (normal.unit(), view.unit());
+        let pbr = self.pbr_at(point);
+        // chance of sampling the specular lobe instead of the cosine
+        let specular = pbr.map_or(0.0, |pbr| 0.5 + 0.5 * pbr.metallic);
+
+        let to_light = match pbr {
+            Some(pbr) if rng.next() < specular => {
+                let h = pbr.sample_half_vector(n, rng);
+                h * (2.0 * v.dot(h)) - v
+            }
+            _ => rng.cosine_hemisphere(n),
+        };
+        let n_dot_l = n.dot(to_light);
+        if n_dot_l <= 0.0 {
+            return (to_light, Rgb::BLACK);
+        }
+
+        let mut pdf = (1.0 - specular) * n_dot_l / PI;
+        if let Some(pbr) = pbr {
+            let h = (to_light + v).unit();
+            let n_dot_h = n.dot(h).max(0.0);
+            pdf += specular * pbr.distribution(n_dot_h) * n_dot_h / (4.0 * v.dot(h).max(1e-4));
+        }
+        // reflect() is scaled by pi, like the lights
+        let weight = self.reflect(point, n, to_light, v) * (1.0 / (PI * pdf));
+        (to_light, weight)
+    }
+
+    // Shading normal with the normal and bump maps applied. `frame` gives the texture
+    // coordinates and the direction u grows along, and is only called when there are maps.
+    pub(crate) fn perturb(&self, normal: Vec3, frame: impl FnOnce() -> ((f64, f64), Vec3)) -> Vec3 {
+        if self.normal_map.is_none() && self.bump_map.is_none() {
+            return normal;
+        }
+        let ((u, v), tangent) = frame();
+
+        let n = normal.unit();
+        let t = tangent - n * n.dot(tangent);
+        if t.length() < 1e-9 {
+            return normal;
+        }
+        let t = t.unit();
+        // up the image, as v grows downwards
+        let b = t.cross(n);
+
+        // empty maps leave the normal alone
+        let mut local = match self.normal_map.as_ref().filter(|map| !map.is_empty()) {
+            Some(map) => {
+                let c = map.sample(u, v);
+                Vec3(2.0 * c.0 - 1.0, 2.0 * c.1 - 1.0, 2.0 * c.2 - 1.0)
+            }
+            None => Vec3(0.0, 0.0, 1.0),
+        };
+        if let Some(bump) = self
+            .bump_map
+            .as_ref()
+            .filter(|bump| !bump.heights.is_empty())
+        {
+            let (width, height) = (bump.heights.width() as f64, bump.heights.height() as f64);
+            let h = |du: f64, dv: f64| bump.heights.sample(u + du, v + dv).0;
+            let dh_du = (h(1.0 / width, 0.0) - h(-1.0 / width, 0.0)) * width / 2.0;
+            let dh_dv = (h(0.0, 1.0 / height) - h(0.0, -1.0 / height)) * height / 2.0;
+            // slopes up the image are against v
+            local = local + Vec3(-dh_du, dh_dv, 0.0) * (bump.strength / width);
+        }
+
+        (t * local.0 + b * local.1 + n * local.2).unit()
+    }
+
+    // PBR parameters with the specular mask roughening the surface where it's below 1
+    pub fn pbr_at(&self, point: Vec3) -> Option<MetallicRoughness> {
+        let (pbr, Some(mask)) = (self.pbr?, self.specular_mask) else {
+            return self.pbr;
+        };
+        Some(MetallicRoughness {
+            roughness: 1.0 - (1.0 - pbr.roughness) * mask.value(point),
+            ..pbr
+        })
+    }
+}
+
+// glTF-style metallic-roughness parameters. Metals tint their reflections with the albedo and
+// have no diffuse term, dielectrics reflect 4% at normal incidence.
+#[derive(Clone, Copy)]
+pub struct MetallicRoughness {
+    pub metallic: f64,
+    pub roughness: f64,
+}
+
+impl MetallicRoughness {
+    // Cook-Torrance with a GGX distribution, Smith-Schlick geometry and Schlick's Fresnel.
+    // Returns the light reflected towards `view` per unit of light arriving from `to_light`,
+    // cosine included. It's scaled by pi so a white diffuse surface lit head-on reflects all
+    // of it, as with the Phong lights.
+    pub(crate) fn reflect(&self, albedo: Rgb, normal: Vec3, to_light: Vec3, view: Vec3) -> Rgb {
+        let (n, l, v) = (normal.unit(), to_light.unit(), view.unit());
+        let n_dot_l = n.dot(l);
+        if n_dot_l <= 0.0 {
+            return Rgb::BLACK;
+        }
+        let n_dot_v = n.dot(v).max(1e-4);
+        let h = (l + v).unit();
+        let n_dot_h = n.dot(h).max(0.0);
+        let v_dot_h = v.dot(h).max(0.0);
+
+        let d = self.distribution(n_dot_h);
+
+        let k = (self.roughness + 1.0).powi(2) / 8.0;
+        let g = n_dot_l / (n_dot_l * (1.0 - k) + k) * n_dot_v / (n_dot_v * (1.0 - k) + k);
+
+        let f0 = Rgb::gray(0.04) * (1.0 - self.metallic) + albedo * self.metallic;
+        let schlick = (1.0 - v_dot_h).powi(5);
+        let f = f0 * (1.0 - schlick) + Rgb::WHITE * schlick;
+
+        let specular = f * (d * g / (4.0 * n_dot_l * n_dot_v));
+        let diffuse = (Rgb::WHITE - f) * albedo * ((1.0 - self.metallic) / PI);
+
+        (diffuse + specular) * (PI * n_dot_l)
+    }
+
+    // GGX density of microfacet normals, for the cosine between one and the surface normal
+    fn distribution(&self, n_dot_h: f64) -> f64 {
+        let alpha = (self.roughness * self.roughness).max(1e-3);
+        let a2 = alpha * alpha;
+        a2 / (PI * (n_dot_h * n_dot_h * (a2 - 1.0) + 1.0).powi(2))
+    }
+
+    // microfacet normal around the unit `normal`, picked with density distribution() times
+    // its cosine to the normal
+    fn sample_half_vector(&self, normal: Vec3, rng: &mut Rng) -> Vec3 {
+        let alpha = (self.roughness * self.roughness).max(1e-3);
+        let a2 = alpha * alpha;
+        let u = rng.next();
+        let cos = ((1.0 - u) / (1.0 + (a2 - 1.0) * u)).sqrt();
+        let sin = (1.0 - cos * cos).sqrt();
+        let angle = 2.0 * PI * rng.next();
+        let (tangent, bitangent) = orthonormal_basis(normal);
+        tangent * (sin * angle.cos()) + bitangent * (sin * angle.sin()) + normal * cos
+    }
+
+    // Light reflected towards `view` from an environment, given the irradiance around the
+    // normal and the radiance around the mirror direction prefiltered for this roughness.
+    // The specular integral uses Karis' analytic fit of the split-sum lookup table.
+    pub(crate) fn reflect_environment(
+        &self,
+        albedo: Rgb,
+        normal: Vec3,
+        view: Vec3,
+        irradiance: Rgb,
+        prefiltered: Rgb,
+    ) -> Rgb {
+        let n_dot_v = normal.unit().dot(view.unit()).max(1e-4);
+        let r = self.roughness;
+        let c = (
+            1.0 - r,
+            0.0425 - 0.0275 * r,
+            1.04 - 0.572 * r,
+            0.022 * r - 0.04,
+        );
+        let a004 = (c.0 * c.0).min(2f64.powf(-9.28 * n_dot_v)) * c.0 + c.1;
+        let (scale, bias) = (c.2 - 1.04 * a004, c.3 + 1.04 * a004);
+
+        let f0 = Rgb::gray(0.04) * (1.0 - self.metallic) + albedo * self.metallic;
+        let specular = f0 * scale + Rgb::gray(bias);
+        let diffuse = albedo * (1.0 - self.metallic);
+
+        diffuse * irradiance + specular * prefiltered
+    }
+}
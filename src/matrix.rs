@@ -9,9 +9,9 @@ impl Matrix {
     pub fn transpose(&self) -> Self {
         let mut output = [[0.0; 4]; 4];
 
-        for i in 0..4 {
-            for j in 0..4 {
-                output[i][j] = self.0[j][i];
+        for (i, row) in output.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.0[j][i];
             }
         }
 
@@ -27,6 +27,17 @@ impl Matrix {
         ])
     }
 
+    pub fn rotation_x(degrees: f64) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+
+        Self([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, cos, sin, 0.0],
+            [0.0, -sin, cos, 0.0],
+            [0.0, 0.0, 0.0, 1.00],
+        ])
+    }
+
     pub fn rotation_y(degrees: f64) -> Self {
         let (sin, cos) = degrees.to_radians().sin_cos();
 
@@ -55,10 +66,8 @@ impl Mul<Vec3> for Matrix {
         let mut out = [0.0; 4];
         let vec = [rhs.0, rhs.1, rhs.2, 1.0];
 
-        for i in 0..4 {
-            for j in 0..4 {
-                out[i] += self.0[i][j] * vec[j];
-            }
+        for (value, row) in out.iter_mut().zip(self.0) {
+            *value = row.iter().zip(vec).map(|(m, v)| m * v).sum();
         }
 
         Vec3(out[0], out[1], out[2])
@@ -71,11 +80,9 @@ impl Mul for Matrix {
     fn mul(self, rhs: Self) -> Self::Output {
         let mut output = [[0.0; 4]; 4];
 
-        for i in 0..4 {
-            for j in 0..4 {
-                for k in 0..4 {
-                    output[i][j] += self.0[i][k] * rhs.0[k][j];
-                }
+        for (i, row) in output.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.0[i][k] * rhs.0[k][j]).sum();
             }
         }
 
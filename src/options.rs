@@ -0,0 +1,182 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::input::{Bindings, Recording};
+use crate::{AviWriter, Canvas, FrameWriter, ImageSequence, Renderer};
+
+// command line options shared by the binaries, described by USAGE
+pub struct Options {
+    pub width: usize,
+    pub height: usize,
+    pub frames: usize,
+    pub output: Option<PathBuf>,
+    pub fps: u32,
+    pub record: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub bindings: PathBuf,
+    pub samples: u32,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 800,
+            frames: 0,
+            output: None,
+            fps: 30,
+            record: None,
+            replay: None,
+            bindings: PathBuf::from("bindings.cfg"),
+            samples: 64,
+        }
+    }
+}
+
+impl Options {
+    pub const USAGE: &'static str = "\
+options:
+  --size <width>x<height>
+  --frames <n>         render a turntable of n frames headlessly to --output
+  --output <path>      directory for a PPM sequence, or a file ending in .avi; frames/ when
+                       only --frames is given
+  --fps <n>            frame rate of AVI output
+  --record <file>      save the interactive session's input
+  --replay <file>      play back recorded input, headlessly when --output is given
+  --bindings <file>    key bindings, bindings.cfg by default
+  --samples <n>        passes a progressive renderer accumulates per frame";
+
+    pub fn from_args() -> io::Result<Self> {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    // every option takes a value; counts and sizes must be positive
+    pub fn parse(args: impl IntoIterator<Item = String>) -> io::Result<Self> {
+        let mut options = Self::default();
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| usage_error(format!("missing value for {arg}")))?;
+            match arg.as_str() {
+                "--frames" => options.frames = positive(&value, "frame count")?,
+                "--output" => options.output = Some(PathBuf::from(value)),
+                "--fps" => options.fps = positive(&value, "frame rate")?,
+                "--record" => options.record = Some(PathBuf::from(value)),
+                "--replay" => options.replay = Some(PathBuf::from(value)),
+                "--bindings" => options.bindings = PathBuf::from(value),
+                "--samples" => options.samples = positive(&value, "sample count")?,
+                "--size" => {
+                    let (width, height) = value.split_once('x').ok_or_else(|| {
+                        usage_error(format!("expected WIDTHxHEIGHT, got {value}"))
+                    })?;
+                    options.width = positive(width, "width")?;
+                    options.height = positive(height, "height")?;
+                }
+                _ => return Err(usage_error(format!("unknown argument {arg}"))),
+            }
+        }
+
+        Ok(options)
+    }
+
+    fn writer(&self, path: &Path) -> io::Result<Box<dyn FrameWriter>> {
+        Ok(if path.extension().is_some_and(|ext| ext == "avi") {
+            Box::new(AviWriter::create(path, self.width, self.height, self.fps)?)
+        } else {
+            Box::new(ImageSequence::new(path)?)
+        })
+    }
+
+    fn configure(&self, mut canvas: Canvas) -> Canvas {
+        canvas.set_passes(self.samples);
+        canvas
+    }
+
+    pub fn run(&self, title: &str, renderer: &mut impl Renderer) -> io::Result<()> {
+        if self.frames > 0 {
+            let output = self.output.as_deref().unwrap_or(Path::new("frames"));
+            let mut writer = self.writer(output)?;
+            let degrees = 360.0 / self.frames as f64;
+            let mut canvas = self.configure(Canvas::headless(self.width, self.height));
+            return canvas.render_frames(
+                renderer,
+                self.frames,
+                |r, _| r.rotate(degrees, 0.0),
+                writer.as_mut(),
+            );
+        }
+
+        if let Some(replay) = &self.replay {
+            let recording = Recording::load(replay)?;
+            return match &self.output {
+                Some(output) => self
+                    .configure(Canvas::headless(self.width, self.height))
+                    .replay(renderer, &recording, Some(self.writer(output)?.as_mut())),
+                None => self
+                    .configure(Canvas::new(title, self.width, self.height))
+                    .replay(renderer, &recording, None),
+            };
+        }
+
+        let mut canvas = self.configure(Canvas::new(title, self.width, self.height));
+        canvas.set_bindings(Bindings::load_or_default(&self.bindings)?);
+        match &self.record {
+            Some(path) => canvas.record(renderer).save(path),
+            None => {
+                canvas.render(renderer);
+                Ok(())
+            }
+        }
+    }
+}
+
+fn positive<T: FromStr + Default + PartialEq>(value: &str, name: &str) -> io::Result<T> {
+    value
+        .parse()
+        .ok()
+        .filter(|n| *n != T::default())
+        .ok_or_else(|| usage_error(format!("invalid {name} {value}")))
+}
+
+fn usage_error(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> io::Result<Options> {
+        Options::parse(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn parses_options() {
+        let options = parse("--size 640x480 --frames 72 --fps 24 --output out.avi").unwrap();
+        assert_eq!((options.width, options.height), (640, 480));
+        assert_eq!((options.frames, options.fps), (72, 24));
+        assert_eq!(options.output, Some(PathBuf::from("out.avi")));
+    }
+
+    #[test]
+    fn rejects_bad_options() {
+        for args in [
+            "--frames",
+            "--frames x",
+            "--frames 0",
+            "--fps 0",
+            "--samples 0",
+            "--size 640",
+            "--size 0x0",
+            "--size 640x0",
+            "--size -1x480",
+            "--colour red",
+        ] {
+            let error = parse(args).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput, "{args}");
+        }
+    }
+}
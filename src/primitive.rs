@@ -0,0 +1,786 @@
+use std::f64::consts::PI;
+
+use crate::random::orthonormal_basis;
+use crate::{Matrix, Vec3};
+
+const EPSILON: f64 = 1e-9;
+
+#[derive(Clone, Copy)]
+pub struct Hit {
+    pub t: f64,
+    pub normal: Vec3, // outward unit normal
+}
+
+// stretch of a ray inside a solid; flat shapes give spans where enter.t == exit.t
+#[derive(Clone, Copy)]
+pub struct Span {
+    pub enter: Hit,
+    pub exit: Hit,
+}
+
+impl Span {
+    fn new(enter: Hit, exit: Hit) -> Option<Self> {
+        (enter.t <= exit.t).then_some(Self { enter, exit })
+    }
+
+    fn everywhere(normal: Vec3) -> Self {
+        Self {
+            enter: Hit {
+                t: f64::NEG_INFINITY,
+                normal,
+            },
+            exit: Hit {
+                t: f64::INFINITY,
+                normal,
+            },
+        }
+    }
+
+    // overlap of two spans, keeping the hits that bound it
+    fn clip(self, other: Span) -> Option<Self> {
+        let enter = if self.enter.t >= other.enter.t {
+            self.enter
+        } else {
+            other.enter
+        };
+        let exit = if self.exit.t <= other.exit.t {
+            self.exit
+        } else {
+            other.exit
+        };
+        Self::new(enter, exit)
+    }
+}
+
+pub trait Primitive {
+    // every stretch of origin + t * direction inside the shape, sorted by t and disjoint
+    fn spans(&self, origin: Vec3, direction: Vec3) -> Vec<Span>;
+
+    fn bounds(&self) -> Bounds;
+
+    // first surface crossing with t in [t_min, t_max]
+    fn intersect(&self, origin: Vec3, direction: Vec3, t_min: f64, t_max: f64) -> Option<Hit> {
+        self.spans(origin, direction)
+            .into_iter()
+            .flat_map(|span| [span.enter, span.exit])
+            .find(|hit| hit.t >= t_min && hit.t <= t_max)
+    }
+
+    // The same at a moment during the shutter interval, for shapes that move. Bounds must
+    // then cover the whole motion.
+    fn spans_at(&self, origin: Vec3, direction: Vec3, _time: f64) -> Vec<Span> {
+        self.spans(origin, direction)
+    }
+
+    fn intersect_at(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        t_min: f64,
+        t_max: f64,
+        _time: f64,
+    ) -> Option<Hit> {
+        self.intersect(origin, direction, t_min, t_max)
+    }
+
+    // Texture coordinates of a surface point, from 0 to 1 with v growing downwards, and the
+    // direction u grows along. Wraps a globe around the middle of the bounds by default.
+    fn uv(&self, point: Vec3) -> ((f64, f64), Vec3) {
+        let bounds = self.bounds();
+        let center = (bounds.min + bounds.max) * 0.5;
+        let d = if center.length().is_finite() {
+            point - center
+        } else {
+            point
+        };
+        let u = 0.5 - d.0.atan2(d.2) / (2.0 * PI);
+        let v = (d.1 / d.length()).clamp(-1.0, 1.0).acos() / PI;
+        ((u, v), Vec3(-d.2, 0.0, d.0))
+    }
+
+    // the same at a moment during the shutter interval, for shapes that move
+    fn uv_at(&self, point: Vec3, _time: f64) -> ((f64, f64), Vec3) {
+        self.uv(point)
+    }
+}
+
+// axis-aligned bounding box
+#[derive(Clone, Copy)]
+pub struct Bounds {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Bounds {
+    pub const INFINITE: Self = Self {
+        min: Vec3(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        max: Vec3(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+    };
+
+    pub fn around(center: Vec3, radius: f64) -> Self {
+        let extent = Vec3(radius, radius, radius);
+        Self {
+            min: center - extent,
+            max: center + extent,
+        }
+    }
+
+    pub fn union(self, other: Self) -> Self {
+        Self {
+            min: Vec3(
+                self.min.0.min(other.min.0),
+                self.min.1.min(other.min.1),
+                self.min.2.min(other.min.2),
+            ),
+            max: Vec3(
+                self.max.0.max(other.max.0),
+                self.max.1.max(other.max.1),
+                self.max.2.max(other.max.2),
+            ),
+        }
+    }
+
+    pub fn intersection(self, other: Self) -> Self {
+        Self {
+            min: Vec3(
+                self.min.0.max(other.min.0),
+                self.min.1.max(other.min.1),
+                self.min.2.max(other.min.2),
+            ),
+            max: Vec3(
+                self.max.0.min(other.max.0),
+                self.max.1.min(other.max.1),
+                self.max.2.min(other.max.2),
+            ),
+        }
+    }
+
+    // entry and exit distances of the ray through the box (slab test)
+    pub(crate) fn slabs(&self, origin: Vec3, direction: Vec3) -> (f64, f64) {
+        let mut near = f64::NEG_INFINITY;
+        let mut far = f64::INFINITY;
+
+        for (min, max, o, d) in [
+            (self.min.0, self.max.0, origin.0, direction.0),
+            (self.min.1, self.max.1, origin.1, direction.1),
+            (self.min.2, self.max.2, origin.2, direction.2),
+        ] {
+            let (t0, t1) = ((min - o) / d, (max - o) / d);
+            // NaN when the ray lies in a slab's plane; min/max skip it
+            near = near.max(t0.min(t1));
+            far = far.min(t0.max(t1));
+        }
+
+        (near, far)
+    }
+
+    pub fn hit(&self, origin: Vec3, direction: Vec3, t_min: f64, t_max: f64) -> bool {
+        let (near, far) = self.slabs(origin, direction);
+        near <= far && far >= t_min && near <= t_max
+    }
+}
+
+// sorted roots of a t^2 + b t + c
+fn roots(a: f64, b: f64, c: f64) -> Option<(f64, f64)> {
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    let t1 = (-b - discriminant.sqrt()) / (2.0 * a);
+    let t2 = (-b + discriminant.sqrt()) / (2.0 * a);
+    Some((t1.min(t2), t1.max(t2)))
+}
+
+// where the ray is between two parallel planes, `normal` pointing out of the far one
+fn slab(origin: Vec3, direction: Vec3, point: Vec3, normal: Vec3, thickness: f64) -> Option<Span> {
+    let height = (origin - point).dot(normal);
+    let speed = direction.dot(normal);
+
+    if speed.abs() < EPSILON {
+        return (0.0..=thickness)
+            .contains(&height)
+            .then(|| Span::everywhere(normal));
+    }
+
+    let near = Hit {
+        t: -height / speed,
+        normal: -normal,
+    };
+    let far = Hit {
+        t: (thickness - height) / speed,
+        normal,
+    };
+    if speed > 0.0 {
+        Span::new(near, far)
+    } else {
+        Span::new(far, near)
+    }
+}
+
+pub struct Sphere {
+    pub center: Vec3,
+    pub radius: f64,
+}
+
+impl Primitive for Sphere {
+    fn spans(&self, origin: Vec3, direction: Vec3) -> Vec<Span> {
+        let r = self.radius;
+        let center_to_origin = origin - self.center;
+
+        let a = direction.dot(direction);
+        let b = 2.0 * center_to_origin.dot(direction);
+        let c = center_to_origin.dot(center_to_origin) - r * r;
+
+        let hit = |t| Hit {
+            t,
+            normal: (origin + t * direction - self.center) / r,
+        };
+        roots(a, b, c)
+            .and_then(|(t1, t2)| Span::new(hit(t1), hit(t2)))
+            .into_iter()
+            .collect()
+    }
+
+    fn bounds(&self) -> Bounds {
+        Bounds::around(self.center, self.radius)
+    }
+}
+
+// Infinite plane of points where normal . point + distance = 0. As a solid it's the
+// half-space behind the normal.
+pub struct Plane {
+    pub normal: Vec3,
+    pub distance: f64,
+}
+
+impl Primitive for Plane {
+    fn spans(&self, origin: Vec3, direction: Vec3) -> Vec<Span> {
+        let normal = self.normal.unit();
+        let offset = self.distance / self.normal.length();
+        let denominator = normal.dot(direction);
+        let height = normal.dot(origin) + offset;
+
+        if denominator.abs() < EPSILON {
+            return if height <= 0.0 {
+                vec![Span::everywhere(normal)]
+            } else {
+                Vec::new()
+            };
+        }
+
+        let crossing = Hit {
+            t: -height / denominator,
+            normal,
+        };
+        let mut span = Span::everywhere(normal);
+        if denominator < 0.0 {
+            span.enter = crossing;
+        } else {
+            span.exit = crossing;
+        }
+        vec![span]
+    }
+
+    fn bounds(&self) -> Bounds {
+        Bounds::INFINITE
+    }
+
+    // tiles the texture once per unit along the plane
+    fn uv(&self, point: Vec3) -> ((f64, f64), Vec3) {
+        let normal = self.normal.unit();
+        let (tangent, _) = orthonormal_basis(normal);
+        let u = point.dot(tangent);
+        let v = point.dot(normal.cross(tangent));
+        ((u.rem_euclid(1.0), v.rem_euclid(1.0)), tangent)
+    }
+}
+
+pub struct Disc {
+    pub center: Vec3,
+    pub normal: Vec3,
+    pub radius: f64,
+}
+
+impl Primitive for Disc {
+    fn spans(&self, origin: Vec3, direction: Vec3) -> Vec<Span> {
+        let normal = self.normal.unit();
+        let denominator = normal.dot(direction);
+        if denominator.abs() < EPSILON {
+            return Vec::new();
+        }
+
+        let t = (self.center - origin).dot(normal) / denominator;
+        if (origin + t * direction - self.center).length() > self.radius {
+            return Vec::new();
+        }
+
+        let hit = Hit { t, normal };
+        vec![Span {
+            enter: hit,
+            exit: hit,
+        }]
+    }
+
+    fn bounds(&self) -> Bounds {
+        Bounds::around(self.center, self.radius)
+    }
+}
+
+pub struct AxisBox {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Primitive for AxisBox {
+    fn spans(&self, origin: Vec3, direction: Vec3) -> Vec<Span> {
+        let (near, far) = self.bounds().slabs(origin, direction);
+
+        let center = (self.min + self.max) / 2.0;
+        let half_size = (self.max - self.min) / 2.0;
+        let hit = |t| Hit {
+            t,
+            normal: box_normal(origin + t * direction - center, half_size),
+        };
+        Span::new(hit(near), hit(far)).into_iter().collect()
+    }
+
+    fn bounds(&self) -> Bounds {
+        Bounds {
+            min: self.min,
+            max: self.max,
+        }
+    }
+}
+
+// the face whose plane the local point is closest to
+fn box_normal(local: Vec3, half_size: Vec3) -> Vec3 {
+    let (x, y, z) = (
+        local.0 / half_size.0,
+        local.1 / half_size.1,
+        local.2 / half_size.2,
+    );
+
+    if x.abs() >= y.abs() && x.abs() >= z.abs() {
+        Vec3(x.signum(), 0.0, 0.0)
+    } else if y.abs() >= z.abs() {
+        Vec3(0.0, y.signum(), 0.0)
+    } else {
+        Vec3(0.0, 0.0, z.signum())
+    }
+}
+
+pub struct OrientedBox {
+    pub center: Vec3,
+    pub half_size: Vec3,
+    pub axes: [Vec3; 3], // orthonormal
+}
+
+impl OrientedBox {
+    // box with its local x axis along `x_axis` and y axis as close to `up` as possible
+    pub fn new(center: Vec3, half_size: Vec3, x_axis: Vec3, up: Vec3) -> Self {
+        let x = x_axis.unit();
+        let z = x.cross(up).unit();
+        let y = z.cross(x);
+
+        Self {
+            center,
+            half_size,
+            axes: [x, y, z],
+        }
+    }
+
+    fn to_local(&self, v: Vec3) -> Vec3 {
+        Vec3(
+            v.dot(self.axes[0]),
+            v.dot(self.axes[1]),
+            v.dot(self.axes[2]),
+        )
+    }
+
+    fn to_world(&self, v: Vec3) -> Vec3 {
+        self.axes[0] * v.0 + self.axes[1] * v.1 + self.axes[2] * v.2
+    }
+}
+
+impl Primitive for OrientedBox {
+    fn spans(&self, origin: Vec3, direction: Vec3) -> Vec<Span> {
+        let local = AxisBox {
+            min: -self.half_size,
+            max: self.half_size,
+        };
+
+        // rotation preserves t
+        let mut spans = local.spans(
+            self.to_local(origin - self.center),
+            self.to_local(direction),
+        );
+        for span in &mut spans {
+            span.enter.normal = self.to_world(span.enter.normal);
+            span.exit.normal = self.to_world(span.exit.normal);
+        }
+        spans
+    }
+
+    fn bounds(&self) -> Bounds {
+        let [x, y, z] = self.axes;
+        let h = self.half_size;
+        let extent =
+            |i: fn(Vec3) -> f64| (i(x) * h.0).abs() + (i(y) * h.1).abs() + (i(z) * h.2).abs();
+        let extent = Vec3(extent(|v| v.0), extent(|v| v.1), extent(|v| v.2));
+
+        Bounds {
+            min: self.center - extent,
+            max: self.center + extent,
+        }
+    }
+}
+
+// capped cylinder from `base` along `axis`
+pub struct Cylinder {
+    pub base: Vec3,
+    pub axis: Vec3,
+    pub height: f64,
+    pub radius: f64,
+}
+
+impl Primitive for Cylinder {
+    fn spans(&self, origin: Vec3, direction: Vec3) -> Vec<Span> {
+        let axis = self.axis.unit();
+        let offset = origin - self.base;
+
+        // components perpendicular to the axis
+        let d = direction - axis * direction.dot(axis);
+        let o = offset - axis * offset.dot(axis);
+
+        let a = d.dot(d);
+        let b = 2.0 * o.dot(d);
+        let c = o.dot(o) - self.radius * self.radius;
+
+        let side = if a < EPSILON {
+            // parallel to the axis
+            (c <= 0.0).then(|| Span::everywhere(axis))
+        } else {
+            let hit = |t| Hit {
+                t,
+                normal: (o + t * d) / self.radius,
+            };
+            roots(a, b, c).and_then(|(t1, t2)| Span::new(hit(t1), hit(t2)))
+        };
+
+        side.zip(slab(origin, direction, self.base, axis, self.height))
+            .and_then(|(side, caps)| side.clip(caps))
+            .into_iter()
+            .collect()
+    }
+
+    fn bounds(&self) -> Bounds {
+        let top = self.base + self.axis.unit() * self.height;
+        Bounds::around(self.base, self.radius).union(Bounds::around(top, self.radius))
+    }
+}
+
+// capped cone with its base disc at `base` and its apex `height` along `axis`
+pub struct Cone {
+    pub base: Vec3,
+    pub axis: Vec3,
+    pub height: f64,
+    pub radius: f64,
+}
+
+impl Cone {
+    fn apex(&self) -> Vec3 {
+        self.base + self.axis.unit() * self.height
+    }
+}
+
+impl Primitive for Cone {
+    fn spans(&self, origin: Vec3, direction: Vec3) -> Vec<Span> {
+        // w points from the apex towards the base
+        let w = -self.axis.unit();
+        let apex = self.apex();
+        let v = origin - apex;
+        let cos2 =
+            self.height * self.height / (self.height * self.height + self.radius * self.radius);
+
+        // inside the double cone where a t^2 + b t + c >= 0
+        let a = direction.dot(w).powi(2) - cos2 * direction.dot(direction);
+        let b = 2.0 * (direction.dot(w) * v.dot(w) - cos2 * direction.dot(v));
+        let c = v.dot(w).powi(2) - cos2 * v.dot(v);
+
+        let hit = |t| {
+            let m = v + t * direction;
+            Hit {
+                t,
+                normal: (cos2 * m - m.dot(w) * w).unit(),
+            }
+        };
+        let everywhere = Span::everywhere(w);
+        let double_cone = match roots(a, b, c) {
+            Some((t1, t2)) if a < 0.0 => Span::new(hit(t1), hit(t2)).into_iter().collect(),
+            Some((t1, t2)) => vec![
+                Span {
+                    exit: hit(t1),
+                    ..everywhere
+                },
+                Span {
+                    enter: hit(t2),
+                    ..everywhere
+                },
+            ],
+            None if c >= 0.0 => vec![everywhere],
+            None => Vec::new(),
+        };
+
+        // the slab from apex to base discards the upper nappe and adds the base cap
+        let Some(caps) = slab(origin, direction, apex, w, self.height) else {
+            return Vec::new();
+        };
+        double_cone
+            .into_iter()
+            .filter_map(|span| span.clip(caps))
+            .collect()
+    }
+
+    fn bounds(&self) -> Bounds {
+        Bounds::around(self.base, self.radius).union(Bounds::around(self.apex(), 0.0))
+    }
+}
+
+pub enum Operation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+// constructive solid geometry: combines two solids by their spans
+pub struct Csg {
+    pub operation: Operation,
+    pub left: Box<dyn Primitive>,
+    pub right: Box<dyn Primitive>,
+}
+
+impl Csg {
+    pub fn union(left: impl Primitive + 'static, right: impl Primitive + 'static) -> Self {
+        Self::new(Operation::Union, left, right)
+    }
+
+    pub fn intersection(left: impl Primitive + 'static, right: impl Primitive + 'static) -> Self {
+        Self::new(Operation::Intersection, left, right)
+    }
+
+    pub fn difference(left: impl Primitive + 'static, right: impl Primitive + 'static) -> Self {
+        Self::new(Operation::Difference, left, right)
+    }
+
+    fn new(
+        operation: Operation,
+        left: impl Primitive + 'static,
+        right: impl Primitive + 'static,
+    ) -> Self {
+        Self {
+            operation,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    fn inside(&self, left: bool, right: bool) -> bool {
+        match self.operation {
+            Operation::Union => left || right,
+            Operation::Intersection => left && right,
+            Operation::Difference => left && !right,
+        }
+    }
+}
+
+impl Primitive for Csg {
+    fn spans(&self, origin: Vec3, direction: Vec3) -> Vec<Span> {
+        self.spans_at(origin, direction, 0.0)
+    }
+
+    fn spans_at(&self, origin: Vec3, direction: Vec3, time: f64) -> Vec<Span> {
+        // boundary crossings of both operands, swept in order
+        let mut events: Vec<(Hit, bool, bool)> = Vec::new(); // hit, is left, entering
+        for (spans, is_left) in [
+            (self.left.spans_at(origin, direction, time), true),
+            (self.right.spans_at(origin, direction, time), false),
+        ] {
+            for span in spans {
+                events.push((span.enter, is_left, true));
+                events.push((span.exit, is_left, false));
+            }
+        }
+        // entries before exits at equal t so touching spans merge
+        events.sort_by(|a, b| a.0.t.total_cmp(&b.0.t).then(b.2.cmp(&a.2)));
+
+        let (mut in_left, mut in_right) = (false, false);
+        let mut enter = None;
+        let mut spans = Vec::new();
+
+        for (mut hit, is_left, entering) in events {
+            let was_inside = self.inside(in_left, in_right);
+            if is_left {
+                in_left = entering;
+            } else {
+                in_right = entering;
+            }
+            let inside = self.inside(in_left, in_right);
+
+            // surfaces carved out by the right operand face the other way
+            if !is_left && matches!(self.operation, Operation::Difference) {
+                hit.normal = -hit.normal;
+            }
+
+            match (was_inside, inside) {
+                (false, true) => enter = Some(hit),
+                (true, false) => spans.extend(enter.take().and_then(|enter| Span::new(enter, hit))),
+                _ => {}
+            }
+        }
+
+        spans
+    }
+
+    fn bounds(&self) -> Bounds {
+        let (left, right) = (self.left.bounds(), self.right.bounds());
+        match self.operation {
+            Operation::Union => left.union(right),
+            Operation::Intersection => left.intersection(right),
+            Operation::Difference => left,
+        }
+    }
+
+    fn intersect_at(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        t_min: f64,
+        t_max: f64,
+        time: f64,
+    ) -> Option<Hit> {
+        self.spans_at(origin, direction, time)
+            .into_iter()
+            .flat_map(|span| [span.enter, span.exit])
+            .find(|hit| hit.t >= t_min && hit.t <= t_max)
+    }
+}
+
+// uniform scale, then rotation about the y axis in degrees, then translation
+#[derive(Clone, Copy)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub yaw: f64,
+    pub scale: f64,
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        translation: Vec3(0.0, 0.0, 0.0),
+        yaw: 0.0,
+        scale: 1.0,
+    };
+
+    fn lerp(self, other: Self, t: f64) -> Self {
+        Self {
+            translation: self.translation * (1.0 - t) + other.translation * t,
+            yaw: self.yaw * (1.0 - t) + other.yaw * t,
+            scale: self.scale * (1.0 - t) + other.scale * t,
+        }
+    }
+}
+
+// Shape placed by a transform that moves from `from` at time 0 to `to` at time 1, with its
+// components interpolated in between so rotations sweep arcs. Static when both are the same.
+pub struct Moving {
+    shape: Box<dyn Primitive>,
+    from: Transform,
+    to: Transform,
+}
+
+impl Moving {
+    // None unless both scales are positive, so the shape never collapses to a point
+    pub fn new(shape: impl Primitive + 'static, from: Transform, to: Transform) -> Option<Self> {
+        let valid = |transform: Transform| transform.scale > 0.0 && transform.scale.is_finite();
+        (valid(from) && valid(to)).then(|| Self {
+            shape: Box::new(shape),
+            from,
+            to,
+        })
+    }
+
+    // the ray in the shape's space at `time`, and the rotation taking normals back out
+    fn to_local(&self, origin: Vec3, direction: Vec3, time: f64) -> (Vec3, Vec3, Matrix) {
+        let transform = self.from.lerp(self.to, time.clamp(0.0, 1.0));
+        let rotation = Matrix::rotation_y(-transform.yaw);
+
+        // scaling the direction along with the origin preserves t
+        (
+            rotation * (origin - transform.translation) / transform.scale,
+            rotation * direction / transform.scale,
+            Matrix::rotation_y(transform.yaw),
+        )
+    }
+}
+
+impl Primitive for Moving {
+    fn spans(&self, origin: Vec3, direction: Vec3) -> Vec<Span> {
+        self.spans_at(origin, direction, 0.0)
+    }
+
+    fn intersect(&self, origin: Vec3, direction: Vec3, t_min: f64, t_max: f64) -> Option<Hit> {
+        self.intersect_at(origin, direction, t_min, t_max, 0.0)
+    }
+
+    fn spans_at(&self, origin: Vec3, direction: Vec3, time: f64) -> Vec<Span> {
+        let (origin, direction, to_world) = self.to_local(origin, direction, time);
+        let mut spans = self.shape.spans_at(origin, direction, time);
+        for span in &mut spans {
+            span.enter.normal = to_world * span.enter.normal;
+            span.exit.normal = to_world * span.exit.normal;
+        }
+        spans
+    }
+
+    fn intersect_at(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        t_min: f64,
+        t_max: f64,
+        time: f64,
+    ) -> Option<Hit> {
+        let (origin, direction, to_world) = self.to_local(origin, direction, time);
+        let mut hit = self
+            .shape
+            .intersect_at(origin, direction, t_min, t_max, time)?;
+        hit.normal = to_world * hit.normal;
+        Some(hit)
+    }
+
+    fn uv(&self, point: Vec3) -> ((f64, f64), Vec3) {
+        self.uv_at(point, 0.0)
+    }
+
+    // the shape's own coordinates, so textures move along with it
+    fn uv_at(&self, point: Vec3, time: f64) -> ((f64, f64), Vec3) {
+        let (point, _, to_world) = self.to_local(point, Vec3(0.0, 0.0, 0.0), time);
+        let (uv, tangent) = self.shape.uv_at(point, time);
+        (uv, to_world * tangent)
+    }
+
+    // sphere around the shape, swept along the translation at its widest
+    fn bounds(&self) -> Bounds {
+        let local = self.shape.bounds();
+        let center = (local.min + local.max) / 2.0;
+        let radius = (local.max - local.min).length() / 2.0;
+        if !radius.is_finite() {
+            return Bounds::INFINITE;
+        }
+
+        // rotation keeps the shape within this distance of the local origin
+        let reach = (center.length() + radius) * self.from.scale.max(self.to.scale);
+        Bounds::around(self.from.translation, reach)
+            .union(Bounds::around(self.to.translation, reach))
+    }
+}
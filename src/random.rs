@@ -0,0 +1,54 @@
+use std::f64::consts::PI;
+
+use crate::Vec3;
+
+// xorshift64*, small and deterministic so renders are reproducible
+pub struct Rng(u64);
+
+impl Rng {
+    // independent streams for each pixel and pass
+    pub fn new(x: i32, y: i32, pass: u32) -> Self {
+        let mut seed = (x as u32 as u64) << 32 | y as u32 as u64;
+        seed ^= (pass as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        // splitmix64 finalizer spreads nearby seeds apart
+        seed = (seed ^ (seed >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        seed = (seed ^ (seed >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        Self((seed ^ (seed >> 31)) | 1)
+    }
+
+    // uniform in [0, 1)
+    pub fn next(&mut self) -> f64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // uniform in the unit disc
+    pub fn disc(&mut self) -> (f64, f64) {
+        let r = self.next().sqrt();
+        let (sin, cos) = (2.0 * PI * self.next()).sin_cos();
+        (r * cos, r * sin)
+    }
+
+    // unit vector around `normal` with probability proportional to the cosine
+    pub fn cosine_hemisphere(&mut self, normal: Vec3) -> Vec3 {
+        let (x, y) = self.disc();
+        let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+        let (tangent, bitangent) = orthonormal_basis(normal);
+
+        tangent * x + bitangent * y + normal * z
+    }
+}
+
+// two unit vectors perpendicular to the unit `normal` and to each other
+pub fn orthonormal_basis(normal: Vec3) -> (Vec3, Vec3) {
+    let helper = if normal.0.abs() > 0.9 {
+        Vec3(0.0, 1.0, 0.0)
+    } else {
+        Vec3(1.0, 0.0, 0.0)
+    };
+    let tangent = normal.cross(helper).unit();
+
+    (tangent, normal.cross(tangent))
+}
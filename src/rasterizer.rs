@@ -1,6 +1,14 @@
+use std::collections::HashMap;
 use std::f64::consts::PI;
+use std::io::{self, ErrorKind};
+use std::sync::Arc;
 
-use crate::{Canvas, Color, Matrix, Renderer, Vec3};
+use crate::draw::line;
+use crate::light::spot_falloff;
+use crate::material::MetallicRoughness;
+use crate::{
+    Attenuation, Background, Canvas, Color, EnvironmentMap, Material, Matrix, Renderer, Rgb, Vec3,
+};
 
 #[derive(Clone, Copy)]
 pub struct Point {
@@ -9,7 +17,16 @@ pub struct Point {
 }
 
 #[derive(Clone, Copy)]
-struct Triangle(pub usize, pub usize, pub usize, pub Color, pub [Vec3; 3]);
+// vertex indices, material index, vertex normals, texture coordinates and tangents
+struct Triangle(
+    pub usize,
+    pub usize,
+    pub usize,
+    pub usize,
+    pub [Vec3; 3],
+    pub [(f64, f64); 3],
+    pub [Vec3; 3],
+);
 
 struct Plane {
     normal: Vec3,
@@ -33,7 +50,10 @@ impl Plane {
     }
 }
 
-struct Model {
+// Triangle mesh in model space. The generators make shapes about the origin that fit in the
+// -1 to 1 cube unless their parameters stretch them, with texture coordinates laid out the way
+// the surface is seen from outside.
+pub struct Model {
     vertices: Vec<Vec3>,
     triangles: Vec<Triangle>,
     bounds_center: Vec3,
@@ -41,7 +61,35 @@ struct Model {
 }
 
 impl Model {
-    fn sphere(divs: u32, color: Color) -> Self {
+    // bounds around the vertices, which shouldn't be empty
+    fn new(vertices: Vec<Vec3>, triangles: Vec<Triangle>) -> Self {
+        let (min, max) = vertices.iter().fold(
+            (
+                Vec3(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+                -Vec3(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            ),
+            |(min, max), v| {
+                (
+                    Vec3(min.0.min(v.0), min.1.min(v.1), min.2.min(v.2)),
+                    Vec3(max.0.max(v.0), max.1.max(v.1), max.2.max(v.2)),
+                )
+            },
+        );
+        let bounds_center = (min + max) * 0.5;
+        let bounds_radius = vertices.iter().fold(0.0f64, |radius, v| {
+            radius.max((*v - bounds_center).length())
+        });
+
+        Model {
+            vertices,
+            triangles,
+            bounds_center,
+            bounds_radius,
+        }
+    }
+
+    // unit sphere made of `divs` slices and stacks
+    pub fn sphere(divs: u32, material: usize) -> Self {
         let delta_angle = 2.0 * PI / divs as f64;
 
         let mut vertices = Vec::new();
@@ -67,19 +115,74 @@ impl Model {
                 let i2 = divs * d + (i + 1) % divs;
                 let tri0 = [i0 as usize, i1 as usize, i2 as usize];
                 let tri1 = [i0 as usize, (i0 + divs) as usize, i1 as usize];
+                for corners in [tri0, tri1] {
+                    triangles.push(Triangle(
+                        corners[0],
+                        corners[1],
+                        corners[2],
+                        material,
+                        corners.map(|i| vertices[i]),
+                        globe_uvs(corners.map(|i| vertices[i])),
+                        [Vec3(0.0, 0.0, 0.0); 3],
+                    ));
+                }
+            }
+        }
+
+        Model {
+            vertices,
+            triangles,
+            bounds_center: Vec3(0.0, 0.0, 0.0),
+            bounds_radius: 1.0,
+        }
+        .with_tangents()
+    }
+
+    // 2x2x2 cube, each face covering the whole texture and with its own material, in the
+    // order +z, +x, -z, -x, +y, -y
+    pub fn cube(materials: [usize; 6]) -> Self {
+        let vertices = vec![
+            Vec3(1.0, 1.0, 1.0),
+            Vec3(-1.0, 1.0, 1.0),
+            Vec3(-1.0, -1.0, 1.0),
+            Vec3(1.0, -1.0, 1.0),
+            Vec3(1.0, 1.0, -1.0),
+            Vec3(-1.0, 1.0, -1.0),
+            Vec3(-1.0, -1.0, -1.0),
+            Vec3(1.0, -1.0, -1.0),
+        ];
+        let faces = [
+            ([0, 1, 2], [0, 2, 3], Vec3(0.0, 0.0, 1.0)),
+            ([4, 0, 3], [4, 3, 7], Vec3(1.0, 0.0, 0.0)),
+            ([5, 4, 7], [5, 7, 6], Vec3(0.0, 0.0, -1.0)),
+            ([1, 5, 6], [1, 6, 2], Vec3(-1.0, 0.0, 0.0)),
+            ([1, 0, 5], [5, 0, 4], Vec3(0.0, 1.0, 0.0)),
+            ([2, 6, 7], [2, 7, 3], Vec3(0.0, -1.0, 0.0)),
+        ];
+
+        let mut triangles = Vec::new();
+        for ((first, second, normal), material) in faces.into_iter().zip(materials) {
+            // seen from outside, with the image upright on the sides and facing +z on the top
+            // and -z on the bottom
+            let up = if normal.1 != 0.0 {
+                Vec3(0.0, 0.0, normal.1)
+            } else {
+                Vec3(0.0, 1.0, 0.0)
+            };
+            let right = normal.cross(up);
+            let uv = |i: usize| {
+                let v: Vec3 = vertices[i];
+                ((v.dot(right) + 1.0) / 2.0, (1.0 - v.dot(up)) / 2.0)
+            };
+            for corners in [first, second] {
                 triangles.push(Triangle(
-                    tri0[0],
-                    tri0[1],
-                    tri0[2],
-                    color,
-                    [vertices[tri0[0]], vertices[tri0[1]], vertices[tri0[2]]],
-                ));
-                triangles.push(Triangle(
-                    tri1[0],
-                    tri1[1],
-                    tri1[2],
-                    color,
-                    [vertices[tri1[0]], vertices[tri1[1]], vertices[tri1[2]]],
+                    corners[0],
+                    corners[1],
+                    corners[2],
+                    material,
+                    [normal; 3],
+                    corners.map(uv),
+                    [Vec3(0.0, 0.0, 0.0); 3],
                 ));
             }
         }
@@ -88,11 +191,542 @@ impl Model {
             vertices,
             triangles,
             bounds_center: Vec3(0.0, 0.0, 0.0),
-            bounds_radius: 1.0,
+            bounds_radius: 3.0f64.sqrt(),
+        }
+        .with_tangents()
+    }
+
+    // unit sphere from a subdivided icosahedron, with evenly sized triangles and no poles
+    pub fn icosphere(subdivisions: u32, material: usize) -> Self {
+        let phi = (1.0 + 5f64.sqrt()) / 2.0;
+        let mut vertices: Vec<Vec3> = [
+            (-1.0, phi, 0.0),
+            (1.0, phi, 0.0),
+            (-1.0, -phi, 0.0),
+            (1.0, -phi, 0.0),
+            (0.0, -1.0, phi),
+            (0.0, 1.0, phi),
+            (0.0, -1.0, -phi),
+            (0.0, 1.0, -phi),
+            (phi, 0.0, -1.0),
+            (phi, 0.0, 1.0),
+            (-phi, 0.0, -1.0),
+            (-phi, 0.0, 1.0),
+        ]
+        .map(|(x, y, z)| Vec3(x, y, z).unit())
+        .to_vec();
+        let mut faces = vec![
+            [0, 11, 5],
+            [0, 5, 1],
+            [0, 1, 7],
+            [0, 7, 10],
+            [0, 10, 11],
+            [1, 5, 9],
+            [5, 11, 4],
+            [11, 10, 2],
+            [10, 7, 6],
+            [7, 1, 8],
+            [3, 9, 4],
+            [3, 4, 2],
+            [3, 2, 6],
+            [3, 6, 8],
+            [3, 8, 9],
+            [4, 9, 5],
+            [2, 4, 11],
+            [6, 2, 10],
+            [8, 6, 7],
+            [9, 8, 1],
+        ];
+
+        // every triangle splits into four, sharing the midpoints of shared edges
+        for _ in 0..subdivisions {
+            let mut midpoints = HashMap::new();
+            let mut midpoint = |a: usize, b: usize, vertices: &mut Vec<Vec3>| {
+                *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                    vertices.push(((vertices[a] + vertices[b]) * 0.5).unit());
+                    vertices.len() - 1
+                })
+            };
+            faces = faces
+                .into_iter()
+                .flat_map(|[a, b, c]| {
+                    let ab = midpoint(a, b, &mut vertices);
+                    let bc = midpoint(b, c, &mut vertices);
+                    let ca = midpoint(c, a, &mut vertices);
+                    [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+                })
+                .collect();
+        }
+
+        let triangles = faces
+            .into_iter()
+            .map(|corners| {
+                let points = corners.map(|i| vertices[i]);
+                Triangle(
+                    corners[0],
+                    corners[1],
+                    corners[2],
+                    material,
+                    points,
+                    globe_uvs(points),
+                    [Vec3(0.0, 0.0, 0.0); 3],
+                )
+            })
+            .collect();
+
+        Model::new(vertices, triangles).with_tangents()
+    }
+
+    // `width` by `depth` rectangle facing +y, split into `divisions` squares along each side
+    pub fn grid(width: f64, depth: f64, divisions: u32, material: usize) -> Self {
+        Self::heightfield(width, depth, divisions, |_, _| 0.0, material)
+    }
+
+    // Terrain over a `width` by `depth` grid, with `height` giving the y of every x and z.
+    // Normals follow the slopes of `height` rather than the triangles.
+    pub fn heightfield(
+        width: f64,
+        depth: f64,
+        divisions: u32,
+        height: impl Fn(f64, f64) -> f64,
+        material: usize,
+    ) -> Self {
+        let divisions = divisions.max(1) as usize;
+        let step = (width.max(depth) / divisions as f64) * 0.5;
+        Self::patch(divisions, divisions, material, |u, v| {
+            // seen from above with +z up the image
+            let (x, z) = (width * (u - 0.5), depth * (0.5 - v));
+            let slope_x = (height(x + step, z) - height(x - step, z)) / (2.0 * step);
+            let slope_z = (height(x, z + step) - height(x, z - step)) / (2.0 * step);
+            (
+                Vec3(x, height(x, z), z),
+                Vec3(-slope_x, 1.0, -slope_z).unit(),
+            )
+        })
+        .with_tangents()
+    }
+
+    // radius 1 from y -1 to 1, with caps
+    pub fn cylinder(divs: u32, material: usize) -> Self {
+        let side = [
+            (1.0, 1.0, Vec3(1.0, 0.0, 0.0)),
+            (1.0, -1.0, Vec3(1.0, 0.0, 0.0)),
+        ];
+        Self::lathe(&side, divs, material)
+            .with_cap(1.0, divs, material)
+            .with_cap(-1.0, divs, material)
+            .with_tangents()
+    }
+
+    // radius 1 base at y -1, tip at y 1
+    pub fn cone(divs: u32, material: usize) -> Self {
+        // the slope drops 2 for every 1 out
+        let normal = Vec3(2.0, 1.0, 0.0).unit();
+        let side = [(0.0, 1.0, normal), (1.0, -1.0, normal)];
+        Self::lathe(&side, divs, material)
+            .with_cap(-1.0, divs, material)
+            .with_tangents()
+    }
+
+    // ring of radius 1 around the y axis, with a tube of radius `thickness`
+    pub fn torus(thickness: f64, divs: u32, material: usize) -> Self {
+        let sides = (divs / 2).max(3);
+        // round the tube from the top, outwards first
+        let profile: Vec<_> = (0..=sides)
+            .map(|i| {
+                let (sin, cos) = (2.0 * PI * i as f64 / sides as f64).sin_cos();
+                (1.0 + thickness * sin, thickness * cos, Vec3(sin, cos, 0.0))
+            })
+            .collect();
+        Self::lathe(&profile, divs, material).with_tangents()
+    }
+
+    // radius 1 hemispheres joined by a cylinder `length` long along y
+    pub fn capsule(length: f64, divs: u32, material: usize) -> Self {
+        let quarter = (divs / 4).max(2);
+        let arc = |from: u32, offset: f64| {
+            (from..=from + quarter).map(move |i| {
+                let (sin, cos) = (PI * i as f64 / (2 * quarter) as f64).sin_cos();
+                (sin, cos + offset, Vec3(sin, cos, 0.0))
+            })
+        };
+        let profile: Vec<_> = arc(0, length / 2.0)
+            .chain(arc(quarter, -length / 2.0))
+            .collect();
+        Self::lathe(&profile, divs, material).with_tangents()
+    }
+
+    // Grid of `columns` by `rows` quads, with `point` giving the position and normal at texture
+    // coordinates u and v. Seen from outside, u should grow to the right and v downwards.
+    fn patch(
+        columns: usize,
+        rows: usize,
+        material: usize,
+        point: impl Fn(f64, f64) -> (Vec3, Vec3),
+    ) -> Self {
+        let mut vertices = Vec::new();
+        let mut normals = Vec::new();
+        let mut uvs = Vec::new();
+        for row in 0..=rows {
+            for column in 0..=columns {
+                let uv = (column as f64 / columns as f64, row as f64 / rows as f64);
+                let (position, normal) = point(uv.0, uv.1);
+                vertices.push(position);
+                normals.push(normal);
+                uvs.push(uv);
+            }
         }
+
+        let index = |column: usize, row: usize| row * (columns + 1) + column;
+        let mut triangles = Vec::new();
+        for row in 0..rows {
+            for column in 0..columns {
+                let (top_left, top_right) = (index(column, row), index(column + 1, row));
+                let (bottom_left, bottom_right) =
+                    (index(column, row + 1), index(column + 1, row + 1));
+                for corners in [
+                    [top_left, top_right, bottom_right],
+                    [top_left, bottom_right, bottom_left],
+                ] {
+                    let [a, b, c] = corners.map(|i| vertices[i]);
+                    // collapsed at the tips of cones and the poles
+                    if triangle_normal(a, b, c).length() < 1e-12 {
+                        continue;
+                    }
+                    triangles.push(Triangle(
+                        corners[0],
+                        corners[1],
+                        corners[2],
+                        material,
+                        corners.map(|i| normals[i]),
+                        corners.map(|i| uvs[i]),
+                        [Vec3(0.0, 0.0, 0.0); 3],
+                    ));
+                }
+            }
+        }
+
+        Model::new(vertices, triangles)
+    }
+
+    // Surface of revolution around the y axis. The profile lists radius, height and the normal
+    // in the x-y plane from top to bottom, with v spread along it by distance.
+    fn lathe(profile: &[(f64, f64, Vec3)], divs: u32, material: usize) -> Self {
+        let mut distances = vec![0.0];
+        for pair in profile.windows(2) {
+            let step = Vec3(pair[1].0 - pair[0].0, pair[1].1 - pair[0].1, 0.0).length();
+            distances.push(distances.last().unwrap() + step);
+        }
+        let total = distances.last().unwrap().max(f64::EPSILON);
+        let rows = profile.len() - 1;
+
+        let mut model = Self::patch(divs.max(3) as usize, rows, material, |u, v| {
+            let (radius, y, normal) = profile[(v * rows as f64).round() as usize];
+            let (sin, cos) = around(u);
+            (
+                Vec3(radius * sin, y, radius * cos),
+                Vec3(normal.0 * sin, normal.1, normal.0 * cos).unit(),
+            )
+        });
+        // texture rows by distance along the profile rather than by profile point
+        for t in &mut model.triangles {
+            for (corner, vertex) in [t.0, t.1, t.2].into_iter().enumerate() {
+                t.5[corner].1 = distances[vertex / (divs.max(3) as usize + 1)] / total;
+            }
+        }
+        model
+    }
+
+    // flat disc of radius 1 closing a lathe at height `y`, facing away from the middle
+    fn with_cap(mut self, y: f64, divs: u32, material: usize) -> Self {
+        let divs = divs.max(3);
+        let normal = Vec3(0.0, y.signum(), 0.0);
+        let center = self.vertices.len();
+        self.vertices.push(Vec3(0.0, y, 0.0));
+        for i in 0..=divs {
+            let (sin, cos) = around(i as f64 / divs as f64);
+            self.vertices.push(Vec3(sin, y, cos));
+        }
+
+        // seen from outside, like the cube's top and bottom
+        let uv = |v: Vec3| ((v.0 + 1.0) / 2.0, (1.0 - v.2 * y.signum()) / 2.0);
+        for i in 0..divs as usize {
+            let mut corners = [center, center + 1 + i, center + 2 + i];
+            let [a, b, c] = corners.map(|i| self.vertices[i]);
+            if triangle_normal(a, b, c).dot(normal) < 0.0 {
+                corners.swap(1, 2);
+            }
+            self.triangles.push(Triangle(
+                corners[0],
+                corners[1],
+                corners[2],
+                material,
+                [normal; 3],
+                corners.map(|i| uv(self.vertices[i])),
+                [Vec3(0.0, 0.0, 0.0); 3],
+            ));
+        }
+
+        Model::new(self.vertices, self.triangles)
+    }
+
+    // Tangents along which u grows, from each triangle's texture coordinates. Corners sharing a
+    // vertex and a normal average them, so smooth surfaces stay smooth and hard edges stay hard.
+    fn with_tangents(mut self) -> Self {
+        let key = |vertex: usize, normal: Vec3| {
+            (
+                vertex,
+                [normal.0.to_bits(), normal.1.to_bits(), normal.2.to_bits()],
+            )
+        };
+
+        let mut sums = HashMap::new();
+        for t in &self.triangles {
+            let (p0, p1, p2) = (self.vertices[t.0], self.vertices[t.1], self.vertices[t.2]);
+            let [(u0, v0), (u1, v1), (u2, v2)] = t.5;
+            let (e1, e2) = (p1 - p0, p2 - p0);
+            let (du1, dv1, du2, dv2) = (u1 - u0, v1 - v0, u2 - u0, v2 - v0);
+            let determinant = du1 * dv2 - du2 * dv1;
+            if determinant.abs() < 1e-12 {
+                continue;
+            }
+            let tangent = (e1 * dv2 - e2 * dv1) / determinant;
+            for (vertex, normal) in [t.0, t.1, t.2].into_iter().zip(t.4) {
+                let sum = sums
+                    .entry(key(vertex, normal))
+                    .or_insert(Vec3(0.0, 0.0, 0.0));
+                *sum = *sum + tangent;
+            }
+        }
+
+        for t in &mut self.triangles {
+            let vertices = [t.0, t.1, t.2];
+            for (i, vertex) in vertices.into_iter().enumerate() {
+                if let Some(sum) = sums.get(&key(vertex, t.4[i])) {
+                    t.6[i] = *sum;
+                }
+            }
+        }
+        self
+    }
+
+    // unit normals of the triangles' planes, zero for degenerate ones
+    fn face_normals(&self) -> Vec<Vec3> {
+        self.triangles
+            .iter()
+            .map(|t| {
+                let normal =
+                    triangle_normal(self.vertices[t.0], self.vertices[t.1], self.vertices[t.2]);
+                if normal.length() > 0.0 {
+                    normal.unit()
+                } else {
+                    normal
+                }
+            })
+            .collect()
+    }
+
+    // Corner normals averaging the faces around each vertex, weighted by their area and their
+    // angle at the vertex. Faces meeting at more than `crease_angle` degrees keep a hard edge.
+    fn smooth_normals(&self, crease_angle: f64) -> Vec<[Vec3; 3]> {
+        let faces = self.face_normals();
+        let mut around = vec![Vec::new(); self.vertices.len()];
+        for (i, t) in self.triangles.iter().enumerate() {
+            for (corner, vertex) in [t.0, t.1, t.2].into_iter().enumerate() {
+                around[vertex].push((i, corner));
+            }
+        }
+
+        // twice the area times the angle, along the face normal
+        let weighted = |i: usize, corner: usize| {
+            let t = &self.triangles[i];
+            let corners = [t.0, t.1, t.2].map(|v| self.vertices[v]);
+            let p = corners[corner];
+            let (a, b) = (corners[(corner + 1) % 3] - p, corners[(corner + 2) % 3] - p);
+            let edges = a.cross(b);
+            let angle = (a.dot(b) / (a.length() * b.length()))
+                .clamp(-1.0, 1.0)
+                .acos();
+            if angle.is_finite() {
+                faces[i] * edges.length() * angle
+            } else {
+                Vec3(0.0, 0.0, 0.0)
+            }
+        };
+
+        let cos_crease = crease_angle.to_radians().cos();
+        self.triangles
+            .iter()
+            .enumerate()
+            .map(|(i, t)| {
+                [t.0, t.1, t.2].map(|vertex| {
+                    let sum = around[vertex]
+                        .iter()
+                        .filter(|(j, _)| faces[*j].dot(faces[i]) >= cos_crease - 1e-9)
+                        .fold(Vec3(0.0, 0.0, 0.0), |sum, &(j, corner)| {
+                            sum + weighted(j, corner)
+                        });
+                    if sum.length() > 0.0 {
+                        sum.unit()
+                    } else {
+                        faces[i]
+                    }
+                })
+            })
+            .collect()
+    }
+
+    // Triangles from the v, vt, vn and f lines of a Wavefront OBJ file, fanning polygons out
+    // from their first corner and ignoring everything else. Faces without normals get smooth
+    // ones. OBJ space is right-handed, so z flips, and so does v as texture rows grow down.
+    fn parse_obj(source: &str, material: usize, crease_angle: f64) -> io::Result<Self> {
+        let mut vertices = Vec::new();
+        let mut uvs = Vec::new();
+        let mut normals = Vec::new();
+        let mut triangles = Vec::new();
+        // whether each triangle came with normals
+        let mut given = Vec::new();
+
+        for (number, line) in source.lines().enumerate() {
+            let error = |message: &str| obj_error(number + 1, message);
+            let line = line.split('#').next().unwrap_or_default();
+            let mut fields = line.split_whitespace();
+            let keyword = fields.next();
+            let numbers: Vec<f64> = match keyword {
+                Some("v" | "vt" | "vn") => fields
+                    .by_ref()
+                    .map(|f| f.parse().map_err(|_| error("malformed number")))
+                    .collect::<io::Result<_>>()?,
+                _ => Vec::new(),
+            };
+
+            match keyword {
+                Some("v") | Some("vn") if numbers.len() < 3 => {
+                    return Err(error("expected three coordinates"))
+                }
+                Some("v") => vertices.push(Vec3(numbers[0], numbers[1], -numbers[2])),
+                Some("vn") => normals.push(Vec3(numbers[0], numbers[1], -numbers[2]).unit()),
+                Some("vt") => {
+                    let u = *numbers
+                        .first()
+                        .ok_or_else(|| error("expected coordinates"))?;
+                    uvs.push((u, 1.0 - numbers.get(1).unwrap_or(&0.0)));
+                }
+                Some("f") => {
+                    let corners = fields
+                        .map(|corner| {
+                            let mut indices = corner.split('/');
+                            let mut index = |count: usize| match indices.next() {
+                                None | Some("") => Ok(None),
+                                Some(index) => obj_index(index, count)
+                                    .map(Some)
+                                    .ok_or_else(|| error("bad index")),
+                            };
+                            let vertex =
+                                index(vertices.len())?.ok_or_else(|| error("bad index"))?;
+                            Ok((vertex, index(uvs.len())?, index(normals.len())?))
+                        })
+                        .collect::<io::Result<Vec<_>>>()?;
+                    if corners.len() < 3 {
+                        return Err(error("faces need at least three corners"));
+                    }
+
+                    for i in 1..corners.len() - 1 {
+                        let fan = [corners[0], corners[i], corners[i + 1]];
+                        let has_normals = fan.iter().all(|c| c.2.is_some());
+                        triangles.push(Triangle(
+                            fan[0].0,
+                            fan[1].0,
+                            fan[2].0,
+                            material,
+                            fan.map(|c| c.2.map_or(Vec3(0.0, 0.0, 0.0), |n| normals[n])),
+                            fan.map(|c| c.1.map_or((0.0, 0.0), |t| uvs[t])),
+                            [Vec3(0.0, 0.0, 0.0); 3],
+                        ));
+                        given.push(has_normals);
+                    }
+                }
+                _ => {}
+            }
+        }
+        if triangles.is_empty() {
+            return Err(obj_error(source.lines().count(), "no faces"));
+        }
+
+        let mut model = Model::new(vertices, triangles);
+        let smooth = model.smooth_normals(crease_angle);
+        for ((t, normals), given) in model.triangles.iter_mut().zip(smooth).zip(given) {
+            if !given {
+                t.4 = normals;
+            }
+        }
+        Ok(model.with_tangents())
     }
 }
 
+// The globe mapping of the raytracer's default, with u running from 1 back to 0 across the seam
+// and taken from the other corners at the poles
+fn globe_uvs(points: [Vec3; 3]) -> [(f64, f64); 3] {
+    let at_pole = |p: Vec3| p.0.abs() < 1e-9 && p.2.abs() < 1e-9;
+    let mut uvs = points.map(|p| {
+        (
+            0.5 - p.0.atan2(p.2) / (2.0 * PI),
+            (p.1 / p.length()).clamp(-1.0, 1.0).acos() / PI,
+        )
+    });
+    let max_u = uvs
+        .iter()
+        .zip(points)
+        .filter(|(_, p)| !at_pole(*p))
+        .fold(0.0f64, |max, (uv, _)| max.max(uv.0));
+    for (uv, p) in uvs.iter_mut().zip(points) {
+        if !at_pole(p) && max_u - uv.0 > 0.5 {
+            uv.0 += 1.0;
+        }
+    }
+
+    let others: Vec<f64> = uvs
+        .iter()
+        .zip(points)
+        .filter(|(_, p)| !at_pole(*p))
+        .map(|(uv, _)| uv.0)
+        .collect();
+    if !others.is_empty() {
+        let u = others.iter().sum::<f64>() / others.len() as f64;
+        for (uv, p) in uvs.iter_mut().zip(points) {
+            if at_pole(p) {
+                uv.0 = u;
+            }
+        }
+    }
+    uvs
+}
+
+// Sine and cosine of the angle round the y axis at u, which starts facing -z and turns towards
+// +x, matching the globe mapping
+fn around(u: f64) -> (f64, f64) {
+    let (sin, cos) = (2.0 * PI * u).sin_cos();
+    (sin, -cos)
+}
+
+// 1-based, or negative counting back from the latest element
+fn obj_index(index: &str, count: usize) -> Option<usize> {
+    let index: i64 = index.parse().ok()?;
+    let index = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+    (0..count as i64).contains(&index).then_some(index as usize)
+}
+
+fn obj_error(line: usize, message: &str) -> io::Error {
+    io::Error::new(
+        ErrorKind::InvalidData,
+        format!("obj: line {line}: {message}"),
+    )
+}
+
 struct Transform {
     scale: f64,
     rotation: f64,
@@ -114,30 +748,197 @@ struct Instance {
 struct Camera {
     position: Vec3,
     rotation: f64,
+    pitch: f64,
+    viewport: Vec3, // width, height, distance to projection plane
     clipping_planes: Vec<Plane>,
 }
 
-enum Light {
-    Point { position: Vec3, intensity: f64 },
-    Directional { direction: Vec3, intensity: f64 },
-    Ambient { intensity: f64 },
+impl Camera {
+    fn orientation(&self) -> Matrix {
+        Matrix::rotation_y(self.rotation) * Matrix::rotation_x(self.pitch)
+    }
+
+    // camera space point back in the world, where textures are evaluated
+    fn to_world(&self, vertex: Vec3) -> Vec3 {
+        self.orientation() * vertex + self.position
+    }
 }
 
-enum ShadingModel {
+// Planes sit at twice the viewport's extent: triangles crossing them are dropped rather
+// than split, so a tight frustum would leave holes along the edges of the screen.
+fn clipping_planes(viewport: Vec3) -> Vec<Plane> {
+    let (x, y) = (viewport.0 / viewport.2, viewport.1 / viewport.2);
+
+    vec![
+        Plane {
+            // near
+            normal: Vec3(0.0, 0.0, 1.0),
+            distance: -1.0,
+        },
+        Plane {
+            // left
+            normal: Vec3(1.0, 0.0, x).unit(),
+            distance: 0.0,
+        },
+        Plane {
+            // right
+            normal: Vec3(-1.0, 0.0, x).unit(),
+            distance: 0.0,
+        },
+        Plane {
+            // top
+            normal: Vec3(0.0, -1.0, y).unit(),
+            distance: 0.0,
+        },
+        Plane {
+            // bottom
+            normal: Vec3(0.0, 1.0, y).unit(),
+            distance: 0.0,
+        },
+    ]
+}
+
+// per-channel intensities, like the raytracer's lights
+pub enum Light {
+    Point {
+        position: Vec3,
+        intensity: Rgb,
+        attenuation: Attenuation,
+    },
+    Directional {
+        direction: Vec3,
+        intensity: Rgb,
+    },
+    Ambient {
+        intensity: Rgb,
+    },
+    // cone half-angles in degrees, fading out from inner to outer
+    Spot {
+        position: Vec3,
+        direction: Vec3,
+        inner: f64,
+        outer: f64,
+        intensity: Rgb,
+        attenuation: Attenuation,
+    },
+    // unshadowed light from every direction, looked up in the map's prefiltered versions
+    Environment {
+        map: Arc<EnvironmentMap>,
+        intensity: Rgb,
+    },
+}
+
+// normal and bump maps only show with Phong shading, which lights every pixel
+pub enum ShadingModel {
     Flat,
     Gouraud,
     Phong,
 }
 
+// what gets drawn of every triangle
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DrawMode {
+    Shaded,
+    // every edge in its material's albedo, front or back
+    Wireframe,
+    // edges of the front faces that nothing hides, in black
+    HiddenLine,
+    Points,
+    // shaded, with the hidden line edges drawn over in black
+    ShadedWireframe,
+}
+
 pub struct Rasterizer {
     camera: Camera,
+    materials: Vec<Material>,
     models: Vec<Model>,
     instances: Vec<Instance>,
     lights: Vec<Light>,
+    background: Background,
     shading_model: ShadingModel,
+    draw_mode: DrawMode,
 }
 
+// lines are drawn slightly in front of their faces so these don't hide them
+const LINE_DEPTH_BIAS: f64 = 1e-2;
+
 impl Rasterizer {
+    // empty scene seen from the origin down +z, to add models to
+    pub fn new(materials: Vec<Material>, lights: Vec<Light>) -> Self {
+        Self {
+            camera: Camera {
+                position: Vec3(0.0, 0.0, 0.0),
+                rotation: 0.0,
+                pitch: 0.0,
+                viewport: VIEWPORT,
+                clipping_planes: clipping_planes(VIEWPORT),
+            },
+            materials,
+            models: Vec::new(),
+            instances: Vec::new(),
+            lights,
+            background: Background::Solid(Rgb::BLACK),
+            shading_model: ShadingModel::Phong,
+            draw_mode: DrawMode::Shaded,
+        }
+    }
+
+    pub fn with_shading_model(mut self, shading_model: ShadingModel) -> Self {
+        self.shading_model = shading_model;
+        self
+    }
+
+    pub fn with_draw_mode(mut self, draw_mode: DrawMode) -> Self {
+        self.draw_mode = draw_mode;
+        self
+    }
+
+    pub fn with_lights(mut self, lights: Vec<Light>) -> Self {
+        self.lights = lights;
+        self
+    }
+
+    pub fn with_background(mut self, background: Background) -> Self {
+        self.background = background;
+        self
+    }
+
+    // replaces the materials the scene's triangles refer to by index
+    pub fn with_materials(mut self, materials: Vec<Material>) -> Self {
+        self.materials = materials;
+        self
+    }
+
+    // Adds a model placed at `position` and scaled, with its triangles' materials indexing
+    // into the scene's.
+    pub fn with_model(mut self, model: Model, position: Vec3, scale: f64) -> Self {
+        self.models.push(model);
+        self.instances.push(Instance {
+            model_idx: self.models.len() - 1,
+            transform: Transform {
+                scale,
+                rotation: 0.0,
+                position,
+            },
+        });
+        self
+    }
+
+    // Adds a Wavefront OBJ model made of one material, placed at `position` and scaled.
+    // Faces without normals get smooth ones, with hard edges between faces meeting at more
+    // than `crease_angle` degrees.
+    pub fn with_obj(
+        self,
+        source: &str,
+        material: usize,
+        crease_angle: f64,
+        position: Vec3,
+        scale: f64,
+    ) -> io::Result<Self> {
+        let model = Model::parse_obj(source, material, crease_angle)?;
+        Ok(self.with_model(model, position, scale))
+    }
+
     fn render_triangle(
         &self,
         canvas: &mut Canvas,
@@ -152,9 +953,10 @@ impl Rasterizer {
             vertices[triangle.2],
         );
 
+        let material = &self.materials[triangle.3];
         let normal = triangle_normal(v0, v1, v2);
         // backface culling
-        let camera_rotation_matrix = Matrix::rotation_y(self.camera.rotation).transpose();
+        let camera_rotation_matrix = self.camera.orientation().transpose();
         // let vertex_to_camera = self.camera.position - v0;
         let vertex_to_camera = -v0;
         if vertex_to_camera.dot(normal) <= 0.0 {
@@ -168,6 +970,9 @@ impl Rasterizer {
         );
 
         let transform = camera_rotation_matrix * Matrix::rotation_y(rotation);
+        // corners in their original order, for normal and bump maps
+        let corners = [v0, v1, v2];
+        let tangents = triangle.6.map(|t| transform * t);
         let (mut normal0, mut normal1, mut normal2) = (
             transform * triangle.4[0],
             transform * triangle.4[1],
@@ -193,14 +998,43 @@ impl Rasterizer {
         let (x02, x012) = edge_interpolate(p0.y, p0.x, p1.y, p1.x, p2.y, p2.x);
         let (z02, z012) = edge_interpolate(p0.y, 1.0 / v0.2, p1.y, 1.0 / v1.2, p2.y, 1.0 / v2.2);
 
+        // Phong lighting is an intensity scaling the albedo, PBR lighting includes it already,
+        // so PBR textures are only evaluated where the lighting is
+        let illumination = |vertex: Vec3, normal| {
+            let point = self.camera.to_world(vertex);
+            match material.pbr_at(point) {
+                None => illumination(
+                    vertex,
+                    normal,
+                    material.specular,
+                    material.specular_strength_at(point),
+                    &self.camera,
+                    &self.lights,
+                ),
+                Some(pbr) => {
+                    let albedo = Rgb::from(material.albedo_at(point));
+                    pbr_illumination(vertex, normal, albedo, pbr, &self.camera, &self.lights)
+                }
+            }
+        };
+        let shade = |light: Rgb, vertex: Vec3| {
+            let lit = match material.pbr {
+                None => material.albedo_at(self.camera.to_world(vertex)) * light,
+                Some(_) => light.to_color(),
+            };
+            lit + material.emission.to_color()
+        };
+
         // flat
-        let intesity = illumination((v0 + v1 + v2) / 3.0, normal, &self.camera, &self.lights);
+        let intesity = illumination((v0 + v1 + v2) / 3.0, normal);
 
         // gouraud
-        let i0 = illumination(v0, normal0, &self.camera, &self.lights);
-        let i1 = illumination(v1, normal1, &self.camera, &self.lights);
-        let i2 = illumination(v2, normal2, &self.camera, &self.lights);
-        let (i02, i012) = edge_interpolate(p0.y, i0, p1.y, i1, p2.y, i2);
+        let i0 = illumination(v0, normal0);
+        let i1 = illumination(v1, normal1);
+        let i2 = illumination(v2, normal2);
+        let (r02, r012) = edge_interpolate(p0.y, i0.0, p1.y, i1.0, p2.y, i2.0);
+        let (g02, g012) = edge_interpolate(p0.y, i0.1, p1.y, i1.1, p2.y, i2.1);
+        let (b02, b012) = edge_interpolate(p0.y, i0.2, p1.y, i1.2, p2.y, i2.2);
 
         // phong
         let (nx02, nx012) = edge_interpolate(p0.y, normal0.0, p1.y, normal1.0, p2.y, normal2.0);
@@ -213,8 +1047,12 @@ impl Rasterizer {
             x_right,
             z_left,
             z_right,
-            i_left,
-            i_right,
+            r_left,
+            r_right,
+            g_left,
+            g_right,
+            b_left,
+            b_right,
             nx_left,
             nx_right,
             ny_left,
@@ -223,11 +1061,13 @@ impl Rasterizer {
             nz_right,
         ) = if x02[m] < x012[m] {
             (
-                x02, x012, z02, z012, i02, i012, nx02, nx012, ny02, ny012, nz02, nz012,
+                x02, x012, z02, z012, r02, r012, g02, g012, b02, b012, nx02, nx012, ny02, ny012,
+                nz02, nz012,
             )
         } else {
             (
-                x012, x02, z012, z02, i012, i02, nx012, nx02, ny012, ny02, nz012, nz02,
+                x012, x02, z012, z02, r012, r02, g012, g02, b012, b02, nx012, nx02, ny012, ny02,
+                nz012, nz02,
             )
         };
 
@@ -237,7 +1077,25 @@ impl Rasterizer {
                     (
                         (
                             (
-                                ((((((y, left_x), right_x), left_z), right_z), left_i), right_i),
+                                (
+                                    (
+                                        (
+                                            (
+                                                (
+                                                    (
+                                                        ((((y, left_x), right_x), left_z), right_z),
+                                                        left_r,
+                                                    ),
+                                                    right_r,
+                                                ),
+                                                left_g,
+                                            ),
+                                            right_g,
+                                        ),
+                                        left_b,
+                                    ),
+                                    right_b,
+                                ),
                                 left_nx,
                             ),
                             right_nx,
@@ -254,8 +1112,12 @@ impl Rasterizer {
             .zip(x_right)
             .zip(z_left)
             .zip(z_right)
-            .zip(i_left)
-            .zip(i_right)
+            .zip(r_left)
+            .zip(r_right)
+            .zip(g_left)
+            .zip(g_right)
+            .zip(b_left)
+            .zip(b_right)
             .zip(nx_left)
             .zip(nx_right)
             .zip(ny_left)
@@ -268,18 +1130,25 @@ impl Rasterizer {
             match self.shading_model {
                 ShadingModel::Flat => {
                     for (x, z) in (lx..=rx).zip(interpolate(lx, left_z, rx, right_z)) {
-                        if canvas.update_depth_buffer(x, y, z) {
-                            canvas.put_pixel(x, y, triangle.3 * intesity);
+                        if canvas.update_depth_buffer(x, y, 1.0 / z) {
+                            let vertex = unproject_vertex(canvas, self.camera.viewport, x, y, z);
+                            canvas.put_pixel(x, y, shade(intesity, vertex));
                         }
                     }
                 }
                 ShadingModel::Gouraud => {
-                    for ((x, z), i) in (lx..=rx)
+                    for (((x, z), r), (g, b)) in (lx..=rx)
                         .zip(interpolate(lx, left_z, rx, right_z))
-                        .zip(interpolate(lx, left_i, rx, right_i))
+                        .zip(interpolate(lx, left_r, rx, right_r))
+                        .zip(
+                            interpolate(lx, left_g, rx, right_g)
+                                .into_iter()
+                                .zip(interpolate(lx, left_b, rx, right_b)),
+                        )
                     {
-                        if canvas.update_depth_buffer(x, y, z) {
-                            canvas.put_pixel(x, y, triangle.3 * i);
+                        if canvas.update_depth_buffer(x, y, 1.0 / z) {
+                            let vertex = unproject_vertex(canvas, self.camera.viewport, x, y, z);
+                            canvas.put_pixel(x, y, shade(Rgb(r, g, b), vertex));
                         }
                     }
                 }
@@ -290,215 +1159,159 @@ impl Rasterizer {
                         .zip(interpolate(lx, left_ny, rx, right_ny))
                         .zip(interpolate(lx, left_nz, rx, right_nz))
                     {
-                        let vertex = unproject_vertex(canvas, x, y, z);
-                        let normal = Vec3(nx, ny, nz);
-                        let intensity = illumination(vertex, normal, &self.camera, &self.lights);
-                        if canvas.update_depth_buffer(x, y, z) {
-                            canvas.put_pixel(x, y, triangle.3 * intensity);
+                        let vertex = unproject_vertex(canvas, self.camera.viewport, x, y, z);
+                        let normal = material.perturb(Vec3(nx, ny, nz), || {
+                            let [a, b, c] = barycentric(vertex, corners);
+                            let [(u0, v0), (u1, v1), (u2, v2)] = triangle.5;
+                            (
+                                (a * u0 + b * u1 + c * u2, a * v0 + b * v1 + c * v2),
+                                tangents[0] * a + tangents[1] * b + tangents[2] * c,
+                            )
+                        });
+                        let intensity = illumination(vertex, normal);
+                        if canvas.update_depth_buffer(x, y, 1.0 / z) {
+                            canvas.put_pixel(x, y, shade(intensity, vertex));
                         }
                     }
                 }
             }
         }
     }
+
+    // fills the canvas with the background seen through each pixel, before any geometry
+    fn draw_background(&self, canvas: &mut Canvas) {
+        let (width, height) = (canvas.width() as i32, canvas.height() as i32);
+        let viewport = self.camera.viewport;
+        let orientation = self.camera.orientation();
+
+        for x in -width / 2..width / 2 {
+            for y in -height / 2..height / 2 {
+                let direction = orientation
+                    * Vec3(
+                        x as f64 * viewport.0 / width as f64,
+                        y as f64 * viewport.1 / height as f64,
+                        viewport.2,
+                    );
+                canvas.put_pixel(x, y, self.background.sample(direction).to_color());
+            }
+        }
+    }
+
     fn render_model(&self, canvas: &mut Canvas, model: &Model, rotation: f64) {
-        let projected: Vec<Point> = model
+        let projected = self.project_model(canvas, model);
+        for t in &model.triangles {
+            self.render_triangle(canvas, t, &model.vertices, &projected, rotation);
+        }
+    }
+
+    fn project_model(&self, canvas: &Canvas, model: &Model) -> Vec<Point> {
+        model
             .vertices
             .iter()
-            .map(|v| project_vertex(canvas, *v))
-            .collect();
+            .map(|v| project_vertex(canvas, self.camera.viewport, *v))
+            .collect()
+    }
+
+    // fills the depth buffer with the front faces without drawing them
+    fn render_depth(&self, canvas: &mut Canvas, model: &Model) {
+        let projected = self.project_model(canvas, model);
+        for t in model
+            .triangles
+            .iter()
+            .filter(|t| front_facing(t, &model.vertices))
+        {
+            let mut corners = [t.0, t.1, t.2].map(|i| (projected[i], 1.0 / model.vertices[i].2));
+            corners.sort_by_key(|(p, _)| p.y);
+            let [(p0, z0), (p1, z1), (p2, z2)] = corners;
+
+            let (x02, x012) = edge_interpolate(p0.y, p0.x, p1.y, p1.x, p2.y, p2.x);
+            let (z02, z012) = edge_interpolate(p0.y, z0, p1.y, z1, p2.y, z2);
+            let m = x02.len() / 2;
+            let (x_left, x_right, z_left, z_right) = if x02[m] < x012[m] {
+                (x02, x012, z02, z012)
+            } else {
+                (x012, x02, z012, z02)
+            };
+
+            for (((y, left_x), right_x), (left_z, right_z)) in (p0.y..=p2.y)
+                .zip(x_left)
+                .zip(x_right)
+                .zip(z_left.into_iter().zip(z_right))
+            {
+                let (lx, rx) = (left_x as i32, right_x as i32);
+                for (x, z) in (lx..=rx).zip(interpolate(lx, left_z, rx, right_z)) {
+                    canvas.update_depth_buffer(x, y, 1.0 / z);
+                }
+            }
+        }
+    }
 
+    // Triangle edges, all of them in their material's albedo or only the unhidden ones of the
+    // front faces in `color`
+    fn render_edges(&self, canvas: &mut Canvas, model: &Model, hidden_line: Option<Color>) {
+        let projected = self.project_model(canvas, model);
         for t in &model.triangles {
-            self.render_triangle(canvas, &t, &model.vertices, &projected, rotation);
+            let color = match hidden_line {
+                Some(_) if !front_facing(t, &model.vertices) => continue,
+                Some(color) => color,
+                None => self.materials[t.3].albedo,
+            };
+            for (a, b) in [(t.0, t.1), (t.1, t.2), (t.2, t.0)] {
+                let (pa, pb) = (projected[a], projected[b]);
+                if hidden_line.is_none() {
+                    canvas.draw_line((pa.x, pa.y), (pb.x, pb.y), color);
+                    continue;
+                }
+
+                // 1/z is linear across the screen
+                let (za, zb) = (1.0 / model.vertices[a].2, 1.0 / model.vertices[b].2);
+                let steps = (pb.x - pa.x).abs().max((pb.y - pa.y).abs()).max(1) as f64;
+                for (i, (x, y)) in line(pa.x, pa.y, pb.x, pb.y).enumerate() {
+                    let z = za + (zb - za) * i as f64 / steps;
+                    if canvas.update_depth_buffer(x, y, (1.0 - LINE_DEPTH_BIAS) / z) {
+                        canvas.put_pixel(x, y, color);
+                    }
+                }
+            }
         }
     }
 
-    pub fn default_scene() -> Self {
-        let cube = Model {
-            vertices: vec![
-                Vec3(1.0, 1.0, 1.0),
-                Vec3(-1.0, 1.0, 1.0),
-                Vec3(-1.0, -1.0, 1.0),
-                Vec3(1.0, -1.0, 1.0),
-                Vec3(1.0, 1.0, -1.0),
-                Vec3(-1.0, 1.0, -1.0),
-                Vec3(-1.0, -1.0, -1.0),
-                Vec3(1.0, -1.0, -1.0),
-            ],
-            triangles: vec![
-                Triangle(
-                    0,
-                    1,
-                    2,
-                    Color::RED,
-                    [
-                        Vec3(0.0, 0.0, 1.0),
-                        Vec3(0.0, 0.0, 1.0),
-                        Vec3(0.0, 0.0, 1.0),
-                    ],
-                ),
-                Triangle(
-                    0,
-                    2,
-                    3,
-                    Color::RED,
-                    [
-                        Vec3(0.0, 0.0, 1.0),
-                        Vec3(0.0, 0.0, 1.0),
-                        Vec3(0.0, 0.0, 1.0),
-                    ],
-                ),
-                Triangle(
-                    4,
-                    0,
-                    3,
-                    Color::GREEN,
-                    [
-                        Vec3(1.0, 0.0, 0.0),
-                        Vec3(1.0, 0.0, 0.0),
-                        Vec3(1.0, 0.0, 0.0),
-                    ],
-                ),
-                Triangle(
-                    4,
-                    3,
-                    7,
-                    Color::GREEN,
-                    [
-                        Vec3(1.0, 0.0, 0.0),
-                        Vec3(1.0, 0.0, 0.0),
-                        Vec3(1.0, 0.0, 0.0),
-                    ],
-                ),
-                Triangle(
-                    5,
-                    4,
-                    7,
-                    Color::BLUE,
-                    [
-                        Vec3(0.0, 0.0, -1.0),
-                        Vec3(0.0, 0.0, -1.0),
-                        Vec3(0.0, 0.0, -1.0),
-                    ],
-                ),
-                Triangle(
-                    5,
-                    7,
-                    6,
-                    Color::BLUE,
-                    [
-                        Vec3(0.0, 0.0, -1.0),
-                        Vec3(0.0, 0.0, -1.0),
-                        Vec3(0.0, 0.0, -1.0),
-                    ],
-                ),
-                Triangle(
-                    1,
-                    5,
-                    6,
-                    Color::YELLOW,
-                    [
-                        Vec3(-1.0, 0.0, 0.0),
-                        Vec3(-1.0, 0.0, 0.0),
-                        Vec3(-1.0, 0.0, 0.0),
-                    ],
-                ),
-                Triangle(
-                    1,
-                    6,
-                    2,
-                    Color::YELLOW,
-                    [
-                        Vec3(-1.0, 0.0, 0.0),
-                        Vec3(-1.0, 0.0, 0.0),
-                        Vec3(-1.0, 0.0, 0.0),
-                    ],
-                ),
-                Triangle(
-                    1,
-                    0,
-                    5,
-                    Color::PURPLE,
-                    [
-                        Vec3(0.0, 1.0, 0.0),
-                        Vec3(0.0, 1.0, 0.0),
-                        Vec3(0.0, 1.0, 0.0),
-                    ],
-                ),
-                Triangle(
-                    5,
-                    0,
-                    4,
-                    Color::PURPLE,
-                    [
-                        Vec3(0.0, 1.0, 0.0),
-                        Vec3(0.0, 1.0, 0.0),
-                        Vec3(0.0, 1.0, 0.0),
-                    ],
-                ),
-                Triangle(
-                    2,
-                    6,
-                    7,
-                    Color::CYAN,
-                    [
-                        Vec3(0.0, -1.0, 0.0),
-                        Vec3(0.0, -1.0, 0.0),
-                        Vec3(0.0, -1.0, 0.0),
-                    ],
-                ),
-                Triangle(
-                    2,
-                    7,
-                    3,
-                    Color::CYAN,
-                    [
-                        Vec3(0.0, -1.0, 0.0),
-                        Vec3(0.0, -1.0, 0.0),
-                        Vec3(0.0, -1.0, 0.0),
-                    ],
-                ),
-            ],
-            bounds_center: Vec3(0.0, 0.0, 0.0),
-            bounds_radius: 3.0f64.sqrt(),
-        };
+    // every vertex of the model's triangles as a 3x3 dot in their material's albedo
+    fn render_points(&self, canvas: &mut Canvas, model: &Model) {
+        let projected = self.project_model(canvas, model);
+        for t in &model.triangles {
+            let color = self.materials[t.3].albedo;
+            for p in [t.0, t.1, t.2].map(|i| projected[i]) {
+                for (dx, dy) in (-1..=1).flat_map(|dx| (-1..=1).map(move |dy| (dx, dy))) {
+                    canvas.put_pixel(p.x + dx, p.y + dy, color);
+                }
+            }
+        }
+    }
 
-        let sphere = Model::sphere(15, Color::GREEN);
+    pub fn default_scene() -> Self {
+        let cube = Model::cube([0, 1, 2, 3, 4, 5]);
 
-        let s2 = 1.0 / 2.0f64.sqrt();
+        let sphere = Model::sphere(15, 1);
 
         Self {
+            materials: [
+                Color::RED,
+                Color::GREEN,
+                Color::BLUE,
+                Color::YELLOW,
+                Color::PURPLE,
+                Color::CYAN,
+            ]
+            .map(|color| Material::new(color).with_specular(50.0))
+            .to_vec(),
             models: vec![cube, sphere],
             camera: Camera {
                 position: Vec3(-3.0, 1.0, 2.0),
                 rotation: -30.0,
-                clipping_planes: vec![
-                    Plane {
-                        // near
-                        normal: Vec3(0.0, 0.0, 1.0),
-                        distance: -1.0,
-                    },
-                    Plane {
-                        // left
-                        normal: Vec3(s2, 0.0, s2),
-                        distance: 0.0,
-                    },
-                    Plane {
-                        // right
-                        normal: Vec3(-s2, 0.0, s2),
-                        distance: 0.0,
-                    },
-                    Plane {
-                        // top
-                        normal: Vec3(0.0, -s2, s2),
-                        distance: 0.0,
-                    },
-                    Plane {
-                        // bottom
-                        normal: Vec3(0.0, s2, s2),
-                        distance: 0.0,
-                    },
-                ],
+                pitch: 0.0,
+                viewport: VIEWPORT,
+                clipping_planes: clipping_planes(VIEWPORT),
             },
             instances: vec![
                 Instance {
@@ -527,70 +1340,101 @@ impl Rasterizer {
                 },
             ],
             lights: vec![
-                Light::Ambient { intensity: 0.2 },
+                Light::Ambient {
+                    intensity: Rgb::gray(0.2),
+                },
                 Light::Directional {
                     direction: Vec3(1.0, 0.0, 1.0),
-                    intensity: 0.2,
+                    intensity: Rgb::gray(0.2),
                 },
                 Light::Point {
                     position: Vec3(-3.0, 2.0, -10.0),
-                    intensity: 0.6,
+                    intensity: Rgb::gray(0.6),
+                    attenuation: Attenuation::None,
                 },
             ],
+            background: Background::Solid(Rgb::WHITE),
             shading_model: ShadingModel::Phong,
+            draw_mode: DrawMode::Shaded,
         }
     }
 }
 
 impl Renderer for Rasterizer {
     fn render(&self, canvas: &mut Canvas) {
-        let camera_matrix = Matrix::rotation_y(self.camera.rotation).transpose()
+        self.draw_background(canvas);
+
+        let camera_matrix = self.camera.orientation().transpose()
             * Matrix::translation(-1.0 * self.camera.position);
 
-        for instance in &self.instances {
-            let transform_matrix = camera_matrix * instance.transform.matrix();
+        let clipped: Vec<(Model, f64)> = self
+            .instances
+            .iter()
+            .filter_map(|instance| {
+                let transform_matrix = camera_matrix * instance.transform.matrix();
+                let clipped_model = transform_and_clip(
+                    &self.camera.clipping_planes,
+                    &self.models[instance.model_idx],
+                    instance.transform.scale,
+                    transform_matrix,
+                )?;
+                Some((clipped_model, instance.transform.rotation))
+            })
+            .collect();
 
-            if let Some(clipped_model) = transform_and_clip(
-                &self.camera.clipping_planes,
-                &self.models[instance.model_idx],
-                instance.transform.scale,
-                transform_matrix,
-            ) {
-                self.render_model(canvas, &clipped_model, instance.transform.rotation);
+        // hidden lines need every face in the depth buffer before the first edge
+        for (model, rotation) in &clipped {
+            match self.draw_mode {
+                DrawMode::Shaded | DrawMode::ShadedWireframe => {
+                    self.render_model(canvas, model, *rotation)
+                }
+                DrawMode::HiddenLine => self.render_depth(canvas, model),
+                DrawMode::Wireframe => self.render_edges(canvas, model, None),
+                DrawMode::Points => self.render_points(canvas, model),
+            }
+        }
+        if matches!(
+            self.draw_mode,
+            DrawMode::HiddenLine | DrawMode::ShadedWireframe
+        ) {
+            for (model, _) in &clipped {
+                self.render_edges(canvas, model, Some(Color::BLACK));
             }
         }
     }
 
-    fn move_up(&mut self) {
-        self.camera.position.1 += 0.5;
-    }
-
-    fn move_down(&mut self) {
-        self.camera.position.1 -= 0.5;
-    }
-
-    fn move_left(&mut self) {
-        self.camera.position.0 -= 0.05;
-    }
-
-    fn move_right(&mut self) {
-        self.camera.position.0 += 0.05;
+    fn next_draw_mode(&mut self) {
+        self.draw_mode = match self.draw_mode {
+            DrawMode::Shaded => DrawMode::Wireframe,
+            DrawMode::Wireframe => DrawMode::HiddenLine,
+            DrawMode::HiddenLine => DrawMode::Points,
+            DrawMode::Points => DrawMode::ShadedWireframe,
+            DrawMode::ShadedWireframe => DrawMode::Shaded,
+        };
     }
 
-    fn move_front(&mut self) {
-        self.camera.position.2 += 0.05;
+    fn next_mode(&mut self) {
+        self.shading_model = match self.shading_model {
+            ShadingModel::Flat => ShadingModel::Gouraud,
+            ShadingModel::Gouraud => ShadingModel::Phong,
+            ShadingModel::Phong => ShadingModel::Flat,
+        };
     }
 
-    fn move_back(&mut self) {
-        self.camera.position.2 -= 0.05;
+    fn translate(&mut self, offset: Vec3) {
+        self.camera.position =
+            self.camera.position + Matrix::rotation_y(self.camera.rotation) * offset;
     }
 
-    fn rotate_left(&mut self) {
-        self.camera.rotation += 5.0;
+    fn rotate(&mut self, yaw: f64, pitch: f64) {
+        self.camera.rotation -= yaw;
+        self.camera.pitch = (self.camera.pitch + pitch).clamp(-89.0, 89.0);
     }
 
-    fn rotate_right(&mut self) {
-        self.camera.rotation -= 5.0;
+    fn zoom(&mut self, factor: f64) {
+        self.camera.viewport.0 *= factor;
+        self.camera.viewport.1 *= factor;
+        self.camera.clipping_planes = clipping_planes(self.camera.viewport);
     }
 }
 
@@ -669,77 +1513,183 @@ fn edge_interpolate<T: Into<f64> + Copy>(
     (x02, x012)
 }
 
+// whether the triangle faces the camera at the origin
+fn front_facing(triangle: &Triangle, vertices: &[Vec3]) -> bool {
+    let v0 = vertices[triangle.0];
+    let normal = triangle_normal(v0, vertices[triangle.1], vertices[triangle.2]);
+    (-v0).dot(normal) > 0.0
+}
+
 fn triangle_normal(v0: Vec3, v1: Vec3, v2: Vec3) -> Vec3 {
     (v1 - v0).cross(v2 - v0)
 }
 
-fn illumination(vertex: Vec3, normal: Vec3, camera: &Camera, lights: &[Light]) -> f64 {
-    let mut il = 0.0;
+// weights of the corners at a point in the triangle's plane
+fn barycentric(point: Vec3, [v0, v1, v2]: [Vec3; 3]) -> [f64; 3] {
+    let (e1, e2, d) = (v1 - v0, v2 - v0, point - v0);
+    let (d11, d12, d22) = (e1.dot(e1), e1.dot(e2), e2.dot(e2));
+    let (d1, d2) = (d.dot(e1), d.dot(e2));
+    let denominator = d11 * d22 - d12 * d12;
+    if denominator.abs() < 1e-12 {
+        return [1.0, 0.0, 0.0];
+    }
+    let b = (d22 * d1 - d12 * d2) / denominator;
+    let c = (d11 * d2 - d12 * d1) / denominator;
+    [1.0 - b - c, b, c]
+}
 
-    for light in lights {
-        let (intensity, vector) = match light {
-            Light::Ambient { intensity } => {
-                il += intensity;
-                continue;
-            }
+// intensity and direction of the light arriving at `vertex`, in camera space
+fn incoming_light(light: &Light, vertex: Vec3, camera: &Camera) -> Option<(Rgb, Vec3)> {
+    match light {
+        Light::Ambient { .. } | Light::Environment { .. } => None,
+
+        Light::Directional {
+            direction,
+            intensity,
+        } => {
+            let camera_matrix = camera.orientation().transpose();
+            Some((*intensity, camera_matrix * *direction))
+        }
 
-            Light::Directional {
-                direction,
-                intensity,
-            } => {
-                let camera_matrix = Matrix::rotation_y(camera.rotation).transpose();
-                (intensity, camera_matrix * *direction)
-            }
+        Light::Point {
+            position,
+            intensity,
+            attenuation,
+        } => {
+            let camera_matrix =
+                camera.orientation().transpose() * Matrix::translation(-camera.position);
+            let vector = camera_matrix * *position + -vertex;
+            Some((*intensity * attenuation.factor(vector.length()), vector))
+        }
 
-            Light::Point {
-                position,
-                intensity,
-            } => {
-                let camera_matrix = Matrix::rotation_y(camera.rotation).transpose()
-                    * Matrix::translation(-camera.position);
-                (intensity, camera_matrix * *position + -vertex)
+        Light::Spot {
+            position,
+            direction,
+            inner,
+            outer,
+            intensity,
+            attenuation,
+        } => {
+            let camera_matrix =
+                camera.orientation().transpose() * Matrix::translation(-camera.position);
+            let vector = camera_matrix * *position + -vertex;
+            let axis = camera.orientation().transpose() * *direction;
+            let falloff = spot_falloff(axis, -vector, *inner, *outer);
+            Some((
+                *intensity * (falloff * attenuation.factor(vector.length())),
+                vector,
+            ))
+        }
+    }
+}
+
+// `strength` scales the highlights
+fn illumination(
+    vertex: Vec3,
+    normal: Vec3,
+    specular: f64,
+    strength: f64,
+    camera: &Camera,
+    lights: &[Light],
+) -> Rgb {
+    let mut il = Rgb::BLACK;
+
+    for light in lights {
+        if let Light::Ambient { intensity } = light {
+            il = il + *intensity;
+        }
+        if let Light::Environment { map, intensity } = light {
+            // the maps are looked up with directions in world space
+            let orientation = camera.orientation();
+            il = il + *intensity * map.irradiance(orientation * normal);
+
+            if specular != -1.0 {
+                // Phong lobe as the roughness of the matching prefiltered level, scaled to
+                // the lobe's share of the hemisphere like the raytracer's estimate
+                let roughness = (2.0 / (specular + 2.0)).sqrt().sqrt();
+                let reflected = orientation * (-vertex).reflect(normal);
+                il = il
+                    + *intensity
+                        * map.specular(reflected, roughness)
+                        * (2.0 / (specular + 1.0) * strength);
             }
+        }
+        let Some((intensity, vector)) = incoming_light(light, vertex, camera) else {
+            continue;
         };
 
         // diffuse
         let cos_alpha = vector.dot(normal) / (vector.length() * normal.length());
         if cos_alpha > 0.0 {
-            il += cos_alpha * intensity;
+            il = il + intensity * cos_alpha;
         }
 
         //specular
-        let reflected = vector.reflect(normal);
-        let view = camera.position - vertex;
-
-        let cos_beta = reflected.dot(view) / (reflected.length() * view.length());
-        if cos_beta > 0.0 {
-            let specular = 50.0;
-            il += cos_beta.powf(specular) * intensity;
+        if specular != -1.0 {
+            let reflected = vector.reflect(normal);
+            // the camera sits at the origin of camera space
+            let view = -vertex;
+
+            let cos_beta = reflected.dot(view) / (reflected.length() * view.length());
+            if cos_beta > 0.0 {
+                il = il + intensity * (cos_beta.powf(specular) * strength);
+            }
         }
     }
 
     il
 }
 
-const VIEWPORT_WIDTH: f64 = 1.0;
-const VIEWPORT_HEIGHT: f64 = 1.0;
-const DISTANCE: f64 = 1.0;
+// light reflected towards the camera by a metallic-roughness surface, albedo included
+fn pbr_illumination(
+    vertex: Vec3,
+    normal: Vec3,
+    albedo: Rgb,
+    pbr: MetallicRoughness,
+    camera: &Camera,
+    lights: &[Light],
+) -> Rgb {
+    let mut radiance = Rgb::BLACK;
+
+    for light in lights {
+        if let Light::Ambient { intensity } = light {
+            radiance = radiance + *intensity * albedo;
+        }
+        if let Light::Environment { map, intensity } = light {
+            let orientation = camera.orientation();
+            let irradiance = map.irradiance(orientation * normal);
+            let reflected = orientation * (-vertex).reflect(normal);
+            let prefiltered = map.specular(reflected, pbr.roughness);
+            radiance = radiance
+                + *intensity
+                    * pbr.reflect_environment(albedo, normal, -vertex, irradiance, prefiltered);
+        }
+        if let Some((intensity, vector)) = incoming_light(light, vertex, camera) {
+            // the camera sits at the origin of camera space
+            radiance = radiance + intensity * pbr.reflect(albedo, normal, vector, -vertex);
+        }
+    }
+
+    radiance
+}
+
+const VIEWPORT: Vec3 = Vec3(1.0, 1.0, 1.0);
 
-fn project_vertex(canvas: &Canvas, v: Vec3) -> Point {
+fn project_vertex(canvas: &Canvas, viewport: Vec3, v: Vec3) -> Point {
     Point {
-        x: (v.0 * DISTANCE / v.2 * canvas.width() as f64 / VIEWPORT_WIDTH) as i32,
-        y: (v.1 * DISTANCE / v.2 * canvas.height() as f64 / VIEWPORT_HEIGHT) as i32,
+        x: (v.0 * viewport.2 / v.2 * canvas.width() as f64 / viewport.0) as i32,
+        y: (v.1 * viewport.2 / v.2 * canvas.height() as f64 / viewport.1) as i32,
     }
 }
 
-fn unproject_vertex(canvas: &Canvas, x: i32, y: i32, z: f64) -> Vec3 {
+fn unproject_vertex(canvas: &Canvas, viewport: Vec3, x: i32, y: i32, z: f64) -> Vec3 {
     let oz = 1.0 / z;
-    let ux = x as f64 * oz / DISTANCE;
-    let uy = y as f64 * oz / DISTANCE;
+    let ux = x as f64 * oz / viewport.2;
+    let uy = y as f64 * oz / viewport.2;
 
     Vec3(
-        ux * VIEWPORT_WIDTH / canvas.width() as f64,
-        uy * VIEWPORT_WIDTH / canvas.height() as f64,
+        ux * viewport.0 / canvas.width() as f64,
+        uy * viewport.1 / canvas.height() as f64,
         oz,
     )
 }
@@ -1,145 +1,458 @@
-use crate::{Canvas, Color, Matrix, Renderer, Vec3};
-
-pub struct Raytracer<'a> {
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use crate::light::{spot_falloff, Attenuation};
+use crate::primitive::{Hit, Plane, Primitive, Sphere};
+use crate::random::{orthonormal_basis, Rng};
+use crate::{
+    Background, Canvas, Color, EnvironmentMap, Material, Matrix, Pattern, Renderer, Rgb, Texture,
+    Vec3,
+};
+
+pub struct Raytracer {
     camera_position: Vec3,
     camera_rotation: f64,
+    camera_pitch: f64,
     viewport: Vec3, // width, height, distance to projection plane
-    spheres: &'a [Sphere],
-    lights: &'a [Light],
+    aperture: f64,  // lens radius, zero for a pinhole camera
+    focus_distance: f64,
+    shutter: (f64, f64), // open and close times, in the time of moving shapes
+    materials: Vec<Material>,
+    objects: Vec<Object>,
+    lights: Vec<Light>,
+    background: Background,
+    integrator: Integrator,
 }
 
-pub enum Light {
-    Point { position: Vec3, intensity: f64 },
-    Directional { direction: Vec3, intensity: f64 },
-    Ambient { intensity: f64 },
+#[derive(Clone, Copy)]
+pub enum Integrator {
+    // direct lighting and perfect mirror reflections
+    Whitted,
+    // Monte Carlo global illumination, refined over several passes
+    PathTracing { max_depth: u32 },
 }
 
-pub struct Sphere {
-    pub center: Vec3,
-    pub radius: f64,
-    pub color: Color,
-    pub specular: f64,
-    pub reflective: f64,
+// Intensities are per channel, so a white light of strength s is Rgb::gray(s). Positional
+// lights fade with distance according to their attenuation. Area lights (rects, discs and
+// spheres) emit their intensity from each unit of surface area, so they get brighter as they
+// grow.
+pub enum Light {
+    Point {
+        position: Vec3,
+        intensity: Rgb,
+        attenuation: Attenuation,
+    },
+    Directional {
+        direction: Vec3,
+        intensity: Rgb,
+    },
+    Ambient {
+        intensity: Rgb,
+    },
+    // point light shining into a cone, fading out between the inner and outer half-angles
+    // given in degrees
+    Spot {
+        position: Vec3,
+        direction: Vec3,
+        inner: f64,
+        outer: f64,
+        intensity: Rgb,
+        attenuation: Attenuation,
+    },
+    // Area lights cast soft shadows by splitting their light across `samples` shadow rays
+    // aimed at random points on them. They emit less towards grazing angles, with the cosine
+    // to the surface normal; rects and discs only shine on the side their normal faces.
+    // Parallelogram spanned by two edges from `corner`, facing along edges.0 × edges.1.
+    Rect {
+        corner: Vec3,
+        edges: (Vec3, Vec3),
+        intensity: Rgb,
+        samples: u32,
+        attenuation: Attenuation,
+    },
+    Disc {
+        center: Vec3,
+        normal: Vec3,
+        radius: f64,
+        intensity: Rgb,
+        samples: u32,
+        attenuation: Attenuation,
+    },
+    Sphere {
+        center: Vec3,
+        radius: f64,
+        intensity: Rgb,
+        samples: u32,
+        attenuation: Attenuation,
+    },
+    // Light arriving from every direction, scaled by `intensity`. Shadow rays are aimed at
+    // the brighter parts of the map, so a few of them find small bright features like the sun.
+    Environment {
+        map: Arc<EnvironmentMap>,
+        intensity: Rgb,
+        samples: u32,
+    },
 }
 
-impl<'a> Raytracer<'a> {
-    pub const BACKGROUND_COLOR: Color = Color(0, 0, 0);
-
-    pub const DEFAULT_SCENE: Self = Self {
-        camera_position: Vec3(0.0, 0.0, 0.0),
-        camera_rotation: 0.0,
-        viewport: Vec3(1.0, 1.0, 1.0),
-        spheres: &[
-            Sphere {
-                center: Vec3(0.0, -1.0, 3.0),
-                radius: 1.0,
-                color: Color(255, 0, 0),
-                specular: 500.0,
-                reflective: 0.2,
-            },
-            Sphere {
-                center: Vec3(2.0, 0.0, 4.0),
-                radius: 1.0,
-                color: Color(0, 0, 255),
-                specular: 500.0,
-                reflective: 0.3,
-            },
-            Sphere {
-                center: Vec3(-2.0, 0.0, 4.0),
-                radius: 1.0,
-                color: Color(0, 255, 0),
-                specular: 10.0,
-                reflective: 0.4,
-            },
-            Sphere {
-                center: Vec3(0.0, -5001.0, 0.0),
-                radius: 5000.0,
-                color: Color(255, 255, 0),
-                specular: 1000.0,
-                reflective: 0.5,
-            },
-        ],
-        lights: &[
-            Light::Ambient { intensity: 0.2 },
-            Light::Point {
-                position: Vec3(2.0, 1.0, 0.0),
-                intensity: 0.6,
-            },
+impl Light {
+    // (direction, intensity, t_max) of the shadow rays from `point`, none for ambient light
+    fn shadow_rays(&self, point: Vec3, rng: &mut Rng) -> Vec<(Vec3, Rgb, f64)> {
+        match *self {
+            Light::Ambient { .. } => Vec::new(),
             Light::Directional {
-                direction: Vec3(1.0, 4.0, 4.0),
-                intensity: 0.2,
-            },
-        ],
-    };
+                direction,
+                intensity,
+            } => vec![(direction, intensity, f64::INFINITY)],
+            Light::Point {
+                intensity,
+                attenuation,
+                ..
+            } => {
+                let direction = self.sample_position(point, rng) - point;
+                let intensity = intensity * attenuation.factor(direction.length());
+                vec![(direction, intensity, 1.0)]
+            }
+            Light::Spot {
+                position,
+                direction,
+                inner,
+                outer,
+                intensity,
+                attenuation,
+            } => {
+                let falloff = spot_falloff(direction, point - position, inner, outer);
+                if falloff > 0.0 {
+                    let direction = position - point;
+                    let intensity = intensity * falloff * attenuation.factor(direction.length());
+                    vec![(direction, intensity, 1.0)]
+                } else {
+                    Vec::new()
+                }
+            }
+            Light::Rect {
+                intensity,
+                samples,
+                attenuation,
+                ..
+            }
+            | Light::Disc {
+                intensity,
+                samples,
+                attenuation,
+                ..
+            }
+            | Light::Sphere {
+                intensity,
+                samples,
+                attenuation,
+                ..
+            } => {
+                let samples = samples.max(1);
+                (0..samples)
+                    .map(|_| {
+                        let target = self.sample_position(point, rng);
+                        let direction = target - point;
+                        let share = attenuation.factor(direction.length())
+                            * self.emitter_weight(point, target)
+                            / samples as f64;
+                        (direction, intensity * share, 1.0)
+                    })
+                    .collect()
+            }
+            Light::Environment {
+                ref map,
+                intensity,
+                samples,
+            } => {
+                let samples = samples.max(1);
+                (0..samples)
+                    .map(|_| {
+                        // estimates the cosine-weighted mean radiance, like an ambient light
+                        let (direction, radiance) = map.sample(rng);
+                        let share = 1.0 / (PI * samples as f64);
+                        (direction, intensity * radiance * share, f64::INFINITY)
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    // cosine at the emitter between its normal at `target` and the shadow ray from `point`,
+    // times the area the target was picked from
+    fn emitter_weight(&self, point: Vec3, target: Vec3) -> f64 {
+        let (normal, area) = match *self {
+            Light::Rect { edges, .. } => {
+                let normal = edges.0.cross(edges.1);
+                (normal.unit(), normal.length())
+            }
+            Light::Disc { normal, radius, .. } => (normal.unit(), PI * radius * radius),
+            Light::Sphere { center, radius, .. } => {
+                let cos_max = visible_cap(point, center, radius);
+                let area = 2.0 * PI * radius * radius * (1.0 - cos_max);
+                ((target - center).unit(), area)
+            }
+            _ => return 1.0,
+        };
+        let direction = target - point;
+        (-direction.dot(normal) / direction.length()).max(0.0) * area
+    }
+
+    // random point on a light with a position, as seen from `point`
+    fn sample_position(&self, point: Vec3, rng: &mut Rng) -> Vec3 {
+        match *self {
+            Light::Point { position, .. } | Light::Spot { position, .. } => position,
+            Light::Rect { corner, edges, .. } => {
+                corner + edges.0 * rng.next() + edges.1 * rng.next()
+            }
+            Light::Disc {
+                center,
+                normal,
+                radius,
+                ..
+            } => {
+                let (u, v) = orthonormal_basis(normal.unit());
+                let (x, y) = rng.disc();
+                center + (u * x + v * y) * radius
+            }
+            // uniformly over the cap of the sphere that can be seen from the point
+            Light::Sphere { center, radius, .. } => {
+                let axis = (point - center).unit();
+                let (u, v) = orthonormal_basis(axis);
+                let cos_max = visible_cap(point, center, radius);
+                let cos = cos_max + (1.0 - cos_max) * rng.next();
+                let sin = (1.0 - cos * cos).sqrt();
+                let angle = 2.0 * PI * rng.next();
+                center + (u * (sin * angle.cos()) + v * (sin * angle.sin()) + axis * cos) * radius
+            }
+            Light::Directional { .. } | Light::Ambient { .. } | Light::Environment { .. } => {
+                unreachable!("light has no position")
+            }
+        }
+    }
+}
+
+pub struct Object {
+    pub shape: Box<dyn Primitive>,
+    pub material: usize, // index into the raytracer's materials
+}
+
+impl Raytracer {
+    pub fn new(materials: Vec<Material>, objects: Vec<Object>, lights: Vec<Light>) -> Self {
+        Self {
+            camera_position: Vec3(0.0, 0.0, 0.0),
+            camera_rotation: 0.0,
+            camera_pitch: 0.0,
+            viewport: Vec3(1.0, 1.0, 1.0),
+            aperture: 0.0,
+            focus_distance: 1.0,
+            shutter: (0.0, 0.0),
+            materials,
+            objects,
+            lights,
+            background: Background::Solid(Rgb::BLACK),
+            integrator: Integrator::Whitted,
+        }
+    }
+
+    pub fn with_background(mut self, background: Background) -> Self {
+        self.background = background;
+        self
+    }
+
+    pub fn with_integrator(mut self, integrator: Integrator) -> Self {
+        self.integrator = integrator;
+        self
+    }
+
+    // Thin lens of the given radius, sharp at `focus_distance` along the view axis. Each sample
+    // starts from a random point on the lens, so out of focus parts blur as passes accumulate.
+    // Focus distances are kept positive, as rays are scaled by their inverse.
+    pub fn with_depth_of_field(mut self, aperture: f64, focus_distance: f64) -> Self {
+        self.aperture = aperture;
+        self.focus_distance = focus_distance.max(1e-3);
+        self
+    }
+
+    // Keeps the shutter open over an interval of the time `Moving` shapes move in, each
+    // sample seeing the scene at a random moment of it, so moving shapes blur as passes
+    // accumulate. 0 to 1 covers a whole move, 0 to 0.5 half of it.
+    pub fn with_shutter(mut self, open: f64, close: f64) -> Self {
+        self.shutter = (open, close);
+        self
+    }
+
+    pub fn default_scene() -> Self {
+        Self::new(
+            vec![
+                Material::new(Color(255, 0, 0))
+                    .with_specular(500.0)
+                    .with_reflective(0.2),
+                Material::new(Color(0, 0, 255))
+                    .with_specular(500.0)
+                    .with_reflective(0.3),
+                Material::new(Color(0, 255, 0))
+                    .with_specular(10.0)
+                    .with_reflective(0.4),
+                Material::new(Color(255, 255, 0))
+                    .with_specular(1000.0)
+                    .with_reflective(0.5)
+                    .with_albedo_texture(Texture::new(
+                        Pattern::Checker { size: 1.0 },
+                        Color(255, 255, 0),
+                        Color(160, 110, 0),
+                    )),
+            ],
+            vec![
+                Object {
+                    shape: Box::new(Sphere {
+                        center: Vec3(0.0, -1.0, 3.0),
+                        radius: 1.0,
+                    }),
+                    material: 0,
+                },
+                Object {
+                    shape: Box::new(Sphere {
+                        center: Vec3(2.0, 0.0, 4.0),
+                        radius: 1.0,
+                    }),
+                    material: 1,
+                },
+                Object {
+                    shape: Box::new(Sphere {
+                        center: Vec3(-2.0, 0.0, 4.0),
+                        radius: 1.0,
+                    }),
+                    material: 2,
+                },
+                Object {
+                    shape: Box::new(Plane {
+                        normal: Vec3(0.0, 1.0, 0.0),
+                        distance: 1.0,
+                    }),
+                    material: 3,
+                },
+            ],
+            vec![
+                Light::Ambient {
+                    intensity: Rgb::gray(0.2),
+                },
+                Light::Point {
+                    position: Vec3(2.0, 1.0, 0.0),
+                    intensity: Rgb::gray(0.6),
+                    attenuation: Attenuation::None,
+                },
+                Light::Directional {
+                    direction: Vec3(1.0, 4.0, 4.0),
+                    intensity: Rgb::gray(0.2),
+                },
+            ],
+        )
+    }
 
-    fn canvas_to_viewport(&self, canvas: &Canvas, x: i32, y: i32) -> Vec3 {
+    fn canvas_to_viewport(&self, canvas: &Canvas, x: f64, y: f64) -> Vec3 {
         Vec3(
-            x as f64 * self.viewport.0 / canvas.width() as f64,
-            y as f64 * self.viewport.1 / canvas.height() as f64,
+            x * self.viewport.0 / canvas.width() as f64,
+            y * self.viewport.1 / canvas.height() as f64,
             self.viewport.2,
         )
     }
 
-    fn compute_lighting(&self, point: Vec3, normal: Vec3, view: Vec3, specular: f64) -> f64 {
-        let mut i = 0.0;
-        for light in self.lights {
-            let (direction, intensity, t_max) = match light {
-                Light::Ambient { intensity } => {
-                    i += intensity;
-                    continue;
-                }
-                Light::Point {
-                    position,
-                    intensity,
-                } => (*position - point, *intensity, 1.0),
-                Light::Directional {
-                    direction,
-                    intensity,
-                } => (*direction, *intensity, f64::INFINITY),
-            };
+    // Origin and direction of a camera ray through the canvas point, leaving from a random
+    // point on the lens when `rng` is given. Directions keep reaching the projection plane at
+    // t = 1 so primary rays can still start there.
+    fn primary_ray(&self, canvas: &Canvas, x: f64, y: f64, rng: Option<&mut Rng>) -> (Vec3, Vec3) {
+        let camera_matrix =
+            Matrix::rotation_y(self.camera_rotation) * Matrix::rotation_x(self.camera_pitch);
+        let direction = self.canvas_to_viewport(canvas, x, y);
+
+        let (lens, direction) = match rng {
+            Some(rng) if self.aperture > 0.0 => {
+                let (u, v) = rng.disc();
+                let lens = Vec3(u, v, 0.0) * self.aperture;
+                let focus = direction * (self.focus_distance / self.viewport.2);
+                (
+                    lens,
+                    (focus - lens) * (self.viewport.2 / self.focus_distance),
+                )
+            }
+            _ => (Vec3(0.0, 0.0, 0.0), direction),
+        };
 
-            // shadow_check
-            if self.any_intersection(point, direction, 0.001, t_max) {
-                continue;
+        (
+            self.camera_position + camera_matrix * lens,
+            camera_matrix * direction,
+        )
+    }
+
+    // whether each full pass samples the lens or the shutter, so passes need averaging
+    fn distributed(&self) -> bool {
+        self.aperture > 0.0 || self.shutter.0 != self.shutter.1
+    }
+
+    // random moment the shutter is open, the opening one without `rng`
+    fn shutter_time(&self, rng: Option<&mut Rng>) -> f64 {
+        match rng {
+            Some(rng) if self.shutter.0 != self.shutter.1 => {
+                self.shutter.0 + (self.shutter.1 - self.shutter.0) * rng.next()
             }
+            _ => self.shutter.0,
+        }
+    }
 
-            // diffuse
-            let normal_dot_direction = normal.dot(direction);
-            if normal_dot_direction > 0.0 {
-                i += intensity * normal_dot_direction / (normal.length() * direction.length())
+    fn compute_lighting(
+        &self,
+        point: Vec3,
+        normal: Vec3,
+        view: Vec3,
+        material: &Material,
+        time: f64,
+        rng: &mut Rng,
+    ) -> Rgb {
+        let mut i = Rgb::BLACK;
+        for light in &self.lights {
+            if let Light::Ambient { intensity } = light {
+                i = i + *intensity;
             }
 
-            //specular
-            if specular != -1.0 {
-                let reflection = direction.reflect(normal);
-                let reflection_dot_view = reflection.dot(view);
-                if reflection_dot_view > 0.0 {
-                    i += intensity
-                        * (reflection_dot_view / (reflection.length() * view.length()))
-                            .powf(specular)
+            for (direction, intensity, t_max) in light.shadow_rays(point, rng) {
+                // shadow_check
+                if self.any_intersection(point, direction, 0.001, t_max, time) {
+                    continue;
                 }
+
+                i = i + intensity * material.phong(point, normal, direction, view);
             }
         }
         i
     }
 
-    fn intersect_ray_sphere(origin: Vec3, direction: Vec3, sphere: &Sphere) -> (f64, f64) {
-        let r = sphere.radius;
-        let center_to_origin = origin - sphere.center;
-
-        let a = direction.dot(direction);
-        let b = 2.0 * center_to_origin.dot(direction);
-        let c = center_to_origin.dot(center_to_origin) - r * r;
-
-        let discriminant = b * b - 4.0 * a * c;
-        if discriminant < 0.0 {
-            (f64::INFINITY, f64::INFINITY)
-        } else {
-            let t1 = (-b + discriminant.sqrt()) / (2.0 * a);
-            let t2 = (-b - discriminant.sqrt()) / (2.0 * a);
+    // light reflected towards `view` by a metallic-roughness surface
+    fn compute_pbr_lighting(
+        &self,
+        point: Vec3,
+        normal: Vec3,
+        view: Vec3,
+        material: &Material,
+        time: f64,
+        rng: &mut Rng,
+    ) -> Rgb {
+        let Some(pbr) = material.pbr_at(point) else {
+            return Rgb::BLACK;
+        };
+        let albedo = Rgb::from(material.albedo_at(point));
+        let mut radiance = Rgb::BLACK;
+        for light in &self.lights {
+            if let Light::Ambient { intensity } = light {
+                radiance = radiance + *intensity * albedo;
+            }
 
-            (t1, t2)
+            for (direction, intensity, t_max) in light.shadow_rays(point, rng) {
+                if !self.any_intersection(point, direction, 0.001, t_max, time) {
+                    radiance = radiance + intensity * pbr.reflect(albedo, normal, direction, view);
+                }
+            }
         }
+        radiance
     }
 
     fn closest_intesection(
@@ -148,31 +461,43 @@ impl<'a> Raytracer<'a> {
         direction: Vec3,
         t_min: f64,
         t_max: f64,
-    ) -> (f64, Option<&Sphere>) {
-        let mut closest_t = f64::INFINITY;
-        let mut closest_sphere = None;
+        time: f64,
+    ) -> Option<(Hit, &Object)> {
+        let mut closest: Option<(Hit, &Object)> = None;
 
-        for sphere in self.spheres {
-            let (t1, t2) = Self::intersect_ray_sphere(origin, direction, sphere);
-
-            if t1 >= t_min && t1 <= t_max && t1 < closest_t {
-                closest_t = t1;
-                closest_sphere = Some(sphere);
+        for object in &self.objects {
+            if !object.shape.bounds().hit(origin, direction, t_min, t_max) {
+                continue;
             }
-            if t2 >= t_min && t2 <= t_max && t2 < closest_t {
-                closest_t = t2;
-                closest_sphere = Some(sphere);
+
+            // narrowing t_max skips hits further than the closest so far
+            let t_max = closest.map_or(t_max, |(hit, _)| hit.t);
+            if let Some(hit) = object
+                .shape
+                .intersect_at(origin, direction, t_min, t_max, time)
+            {
+                closest = Some((hit, object));
             }
         }
 
-        (closest_t, closest_sphere)
+        closest
     }
 
     // optimized early-exit for shadow check
-    fn any_intersection(&self, origin: Vec3, direction: Vec3, t_min: f64, t_max: f64) -> bool {
-        self.spheres.iter().any(|sphere| {
-            let (t1, t2) = Self::intersect_ray_sphere(origin, direction, sphere);
-            (t1 >= t_min && t1 <= t_max) || (t2 >= t_min && t2 <= t_max)
+    fn any_intersection(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        t_min: f64,
+        t_max: f64,
+        time: f64,
+    ) -> bool {
+        self.objects.iter().any(|object| {
+            object.shape.bounds().hit(origin, direction, t_min, t_max)
+                && object
+                    .shape
+                    .intersect_at(origin, direction, t_min, t_max, time)
+                    .is_some()
         })
     }
 
@@ -181,76 +506,291 @@ impl<'a> Raytracer<'a> {
         origin: Vec3,
         direction: Vec3,
         t_min: f64,
-        t_max: f64,
         recursion_depth: u8,
+        time: f64,
+        rng: &mut Rng,
     ) -> Color {
-        if let (closest_t, Some(sphere)) = self.closest_intesection(origin, direction, t_min, t_max)
+        if let Some((hit, object)) =
+            self.closest_intesection(origin, direction, t_min, f64::INFINITY, time)
         {
-            let point = origin + closest_t * direction;
-            let normal = (point - sphere.center).unit();
-            let local_color =
-                sphere.color * self.compute_lighting(point, normal, -direction, sphere.specular);
-
-            let r = sphere.reflective;
-            if recursion_depth == 0 || r <= 0.0 {
-                local_color
-            } else {
+            let point = origin + hit.t * direction;
+            let material = &self.materials[object.material];
+            let normal = material.perturb(facing(hit.normal, direction), || {
+                object.shape.uv_at(point, time)
+            });
+            let lit = match material.pbr {
+                None => {
+                    material.albedo_at(point)
+                        * self.compute_lighting(point, normal, -direction, material, time, rng)
+                }
+                Some(_) => self
+                    .compute_pbr_lighting(point, normal, -direction, material, time, rng)
+                    .to_color(),
+            };
+            let mut color = lit + material.emission.to_color();
+            if recursion_depth == 0 {
+                return color;
+            }
+
+            let t = material.transparency;
+            if t > 0.0 {
+                let transmitted_color =
+                    self.trace_ray(point, direction, 0.001, recursion_depth - 1, time, rng);
+                color = color * (1.0 - t) + transmitted_color * t;
+            }
+
+            let r = material.reflective_at(point);
+            if r > 0.0 {
                 let reflection = (-direction).reflect(normal);
                 let reflected_color =
-                    self.trace_ray(point, reflection, 0.001, f64::INFINITY, recursion_depth - 1);
-
-                local_color * (1.0 - r) + reflected_color * r
+                    self.trace_ray(point, reflection, 0.001, recursion_depth - 1, time, rng);
+                color = color * (1.0 - r) + reflected_color * r;
             }
+
+            color
         } else {
-            Self::BACKGROUND_COLOR
+            self.background.sample(direction).to_color()
         }
     }
+
+    // radiance of paths that escape the scene after a bounce: the background plus ambient
+    // and environment light
+    fn sky(&self, direction: Vec3) -> Rgb {
+        let ambient = self
+            .lights
+            .iter()
+            .fold(Rgb::BLACK, |sum, light| match light {
+                Light::Ambient { intensity } => sum + *intensity,
+                _ => sum,
+            });
+
+        self.background.sample(direction) + ambient + self.environment(direction)
+    }
+
+    // radiance of environment lights
+    fn environment(&self, direction: Vec3) -> Rgb {
+        self.lights
+            .iter()
+            .fold(Rgb::BLACK, |sum, light| match light {
+                Light::Environment { map, intensity, .. } => {
+                    sum + *intensity * map.radiance(direction)
+                }
+                _ => sum,
+            })
+    }
+
+    // light arriving straight from every light but the ambient and environment ones, which
+    // paths find by escaping, reflected towards `view`
+    fn direct_lighting(
+        &self,
+        point: Vec3,
+        normal: Vec3,
+        view: Vec3,
+        material: &Material,
+        time: f64,
+        rng: &mut Rng,
+    ) -> Rgb {
+        let mut i = Rgb::BLACK;
+        for light in &self.lights {
+            if let Light::Environment { .. } = light {
+                continue;
+            }
+            for (direction, intensity, t_max) in light.shadow_rays(point, rng) {
+                let reflected = material.reflect(point, normal, direction, view);
+                if reflected.max_component() > 0.0
+                    && !self.any_intersection(point, direction, 0.001, t_max, time)
+                {
+                    i = i + intensity * reflected;
+                }
+            }
+        }
+        i
+    }
+
+    // A bounce turns into a perfect mirror with probability `reflective`, passes straight
+    // through with probability `transparency`, and otherwise follows the surface's Phong or
+    // PBR reflectance. Light intensities follow the Whitted convention, so both integrators
+    // agree on how bright a directly lit surface is.
+    fn trace_path(
+        &self,
+        mut origin: Vec3,
+        mut direction: Vec3,
+        max_depth: u32,
+        time: f64,
+        rng: &mut Rng,
+    ) -> Rgb {
+        let mut radiance = Rgb::BLACK;
+        let mut throughput = Rgb::WHITE;
+        let mut t_min = 1.0;
+        let mut bounced = false;
+
+        for depth in 0..max_depth {
+            let Some((hit, object)) =
+                self.closest_intesection(origin, direction, t_min, f64::INFINITY, time)
+            else {
+                // ambient and environment light only reach the camera off a surface
+                let sky = if bounced {
+                    self.sky(direction)
+                } else {
+                    self.background.sample(direction)
+                };
+                return radiance + throughput * sky;
+            };
+
+            let point = origin + hit.t * direction;
+            let material = &self.materials[object.material];
+            let normal = material.perturb(facing(hit.normal, direction), || {
+                object.shape.uv_at(point, time)
+            });
+            radiance = radiance + throughput * material.emission;
+
+            let view = -direction;
+            let reflective = material.reflective_at(point);
+            let choice = rng.next();
+            if choice < reflective {
+                direction = view.reflect(normal);
+                bounced = true;
+            } else if choice < reflective + material.transparency {
+                // carries on in the same direction
+            } else {
+                let direct = self.direct_lighting(point, normal, view, material, time, rng);
+                radiance = radiance + throughput * direct;
+                let (next, weight) = material.sample_reflection(point, normal, view, rng);
+                throughput = throughput * weight;
+                direction = next;
+                bounced = true;
+            }
+            origin = point;
+            t_min = 0.001;
+
+            // russian roulette ends dim paths early without biasing the estimate
+            if depth >= 3 {
+                let survival = throughput.max_component().min(0.95);
+                if rng.next() >= survival {
+                    break;
+                }
+                throughput = throughput * (1.0 / survival);
+            }
+        }
+
+        radiance
+    }
+}
+
+// cosine of the angle from the sphere's center between the direction to `point` and the edge
+// of what can be seen from there, 1 from inside
+fn visible_cap(point: Vec3, center: Vec3, radius: f64) -> f64 {
+    (radius / (point - center).length()).min(1.0)
+}
+
+// planes and discs are visible from behind
+fn facing(normal: Vec3, direction: Vec3) -> Vec3 {
+    if normal.dot(direction) > 0.0 {
+        -normal
+    } else {
+        normal
+    }
 }
 
-impl<'a> Renderer for Raytracer<'a> {
+impl Renderer for Raytracer {
     fn render(&self, canvas: &mut Canvas) {
         let canvas_width = canvas.width() as i32;
         let canvas_height = canvas.height() as i32;
 
-        for x in -canvas_width / 2..canvas_width / 2 {
-            for y in -canvas_height / 2..canvas_height / 2 {
-                let direction = Matrix::rotation_y(self.camera_rotation)
-                    * self.canvas_to_viewport(canvas, x, y);
-                let color = self.trace_ray(self.camera_position, direction, 1.0, f64::INFINITY, 3);
-                canvas.put_pixel(x, y, color);
+        let pass = canvas.pass();
+        let block = canvas.block_size();
+
+        for x in (-canvas_width / 2..canvas_width / 2).step_by(block) {
+            for y in (-canvas_height / 2..canvas_height / 2).step_by(block) {
+                if !canvas.needs_sample(x, y) {
+                    continue;
+                }
+
+                match self.integrator {
+                    // with a lens or an open shutter, full passes average samples across them
+                    Integrator::Whitted if self.distributed() && block == 1 => {
+                        let mut rng = Rng::new(x, y, pass);
+                        let (origin, direction) =
+                            self.primary_ray(canvas, x as f64, y as f64, Some(&mut rng));
+                        let time = self.shutter_time(Some(&mut rng));
+                        let color = self.trace_ray(origin, direction, 1.0, 3, time, &mut rng);
+                        canvas.accumulate_pixel(x, y, Rgb::from(color));
+                    }
+                    Integrator::Whitted => {
+                        let mut rng = Rng::new(x, y, pass);
+                        let (origin, direction) =
+                            self.primary_ray(canvas, x as f64, y as f64, None);
+                        let time = self.shutter_time(None);
+                        let color = self.trace_ray(origin, direction, 1.0, 3, time, &mut rng);
+                        canvas.put_block(x, y, color);
+                    }
+                    // previews show a single sample without accumulating it
+                    Integrator::PathTracing { max_depth } if block > 1 => {
+                        let mut rng = Rng::new(x, y, 0);
+                        let (origin, direction) =
+                            self.primary_ray(canvas, x as f64, y as f64, None);
+                        let time = self.shutter_time(None);
+                        let radiance =
+                            self.trace_path(origin, direction, max_depth, time, &mut rng);
+                        canvas.put_block(x, y, radiance.to_color());
+                    }
+                    Integrator::PathTracing { max_depth } => {
+                        let mut rng = Rng::new(x, y, pass);
+                        // jitter within the pixel, antialiasing as samples accumulate
+                        let (dx, dy) = (rng.next() - 0.5, rng.next() - 0.5);
+                        let (origin, direction) =
+                            self.primary_ray(canvas, x as f64 + dx, y as f64 + dy, Some(&mut rng));
+                        let time = self.shutter_time(Some(&mut rng));
+                        let radiance =
+                            self.trace_path(origin, direction, max_depth, time, &mut rng);
+                        canvas.accumulate_pixel(x, y, radiance);
+                    }
+                }
             }
         }
     }
 
-    fn move_up(&mut self) {
-        self.camera_position.1 += 0.05;
+    fn next_mode(&mut self) {
+        self.integrator = match self.integrator {
+            Integrator::Whitted => Integrator::PathTracing { max_depth: 8 },
+            Integrator::PathTracing { .. } => Integrator::Whitted,
+        };
     }
 
-    fn move_down(&mut self) {
-        self.camera_position.1 -= 0.05;
+    fn progressive(&self) -> bool {
+        matches!(self.integrator, Integrator::PathTracing { .. }) || self.distributed()
     }
 
-    fn move_left(&mut self) {
-        self.camera_position.0 -= 0.05;
+    fn previews(&self) -> bool {
+        true
     }
 
-    fn move_right(&mut self) {
-        self.camera_position.0 += 0.05;
-    }
-
-    fn move_front(&mut self) {
-        self.camera_position.2 += 0.5;
+    // focuses on the surface under the pixel, keeping the focus when it's the background
+    fn focus(&mut self, canvas: &Canvas, x: i32, y: i32) -> bool {
+        let (origin, direction) = self.primary_ray(canvas, x as f64, y as f64, None);
+        let time = self.shutter_time(None);
+        match self.closest_intesection(origin, direction, 1.0, f64::INFINITY, time) {
+            // distance along the view axis, as the direction's depth is the viewport's
+            Some((hit, _)) => {
+                self.focus_distance = hit.t * self.viewport.2;
+                true
+            }
+            None => false,
+        }
     }
 
-    fn move_back(&mut self) {
-        self.camera_position.2 -= 0.5;
+    fn translate(&mut self, offset: Vec3) {
+        self.camera_position =
+            self.camera_position + Matrix::rotation_y(self.camera_rotation) * offset;
     }
 
-    fn rotate_left(&mut self) {
-        self.camera_rotation += 5.0;
+    fn rotate(&mut self, yaw: f64, pitch: f64) {
+        self.camera_rotation -= yaw;
+        self.camera_pitch = (self.camera_pitch + pitch).clamp(-89.0, 89.0);
     }
 
-    fn rotate_right(&mut self) {
-        self.camera_rotation -= 5.0;
+    fn zoom(&mut self, factor: f64) {
+        self.viewport.0 *= factor;
+        self.viewport.1 *= factor;
     }
 }
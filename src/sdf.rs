@@ -0,0 +1,226 @@
+use crate::primitive::{Bounds, Hit, Primitive, Span, Sphere};
+use crate::Vec3;
+
+const MAX_STEPS: usize = 256;
+const SURFACE_DISTANCE: f64 = 1e-4;
+
+// Signed distance to a surface: negative inside, and never more than the true distance
+// so sphere tracing can't step through it.
+pub trait DistanceField {
+    fn distance(&self, point: Vec3) -> f64;
+
+    fn bounds(&self) -> Bounds;
+}
+
+impl DistanceField for Sphere {
+    fn distance(&self, point: Vec3) -> f64 {
+        (point - self.center).length() - self.radius
+    }
+
+    fn bounds(&self) -> Bounds {
+        Primitive::bounds(self)
+    }
+}
+
+// ring around the y axis
+pub struct Torus {
+    pub center: Vec3,
+    pub major_radius: f64,
+    pub minor_radius: f64,
+}
+
+impl DistanceField for Torus {
+    fn distance(&self, point: Vec3) -> f64 {
+        let p = point - self.center;
+        let ring = Vec3(p.0, 0.0, p.2).length() - self.major_radius;
+        Vec3(ring, p.1, 0.0).length() - self.minor_radius
+    }
+
+    fn bounds(&self) -> Bounds {
+        let extent = self.major_radius + self.minor_radius;
+        Bounds {
+            min: self.center - Vec3(extent, self.minor_radius, extent),
+            max: self.center + Vec3(extent, self.minor_radius, extent),
+        }
+    }
+}
+
+// axis-aligned box with edges rounded by `radius`, within `half_size`
+pub struct RoundBox {
+    pub center: Vec3,
+    pub half_size: Vec3,
+    pub radius: f64,
+}
+
+impl DistanceField for RoundBox {
+    fn distance(&self, point: Vec3) -> f64 {
+        let p = point - self.center;
+        let q = Vec3(
+            p.0.abs() - self.half_size.0 + self.radius,
+            p.1.abs() - self.half_size.1 + self.radius,
+            p.2.abs() - self.half_size.2 + self.radius,
+        );
+        let outside = Vec3(q.0.max(0.0), q.1.max(0.0), q.2.max(0.0)).length();
+        let inside = q.0.max(q.1).max(q.2).min(0.0);
+
+        outside + inside - self.radius
+    }
+
+    fn bounds(&self) -> Bounds {
+        Bounds {
+            min: self.center - self.half_size,
+            max: self.center + self.half_size,
+        }
+    }
+}
+
+// polynomial smooth minimum, blending over a distance of `k`
+fn smooth_min(a: f64, b: f64, k: f64) -> f64 {
+    if k <= 0.0 {
+        return a.min(b);
+    }
+    let h = (k - (a - b).abs()).max(0.0) / k;
+    a.min(b) - h * h * k / 4.0
+}
+
+pub struct SmoothUnion {
+    pub left: Box<dyn DistanceField>,
+    pub right: Box<dyn DistanceField>,
+    pub smoothness: f64,
+}
+
+impl DistanceField for SmoothUnion {
+    fn distance(&self, point: Vec3) -> f64 {
+        smooth_min(
+            self.left.distance(point),
+            self.right.distance(point),
+            self.smoothness,
+        )
+    }
+
+    fn bounds(&self) -> Bounds {
+        // the blend only bulges out by up to smoothness / 4
+        let bounds = self.left.bounds().union(self.right.bounds());
+        let margin = Vec3(1.0, 1.0, 1.0) * (self.smoothness / 4.0);
+        Bounds {
+            min: bounds.min - margin,
+            max: bounds.max + margin,
+        }
+    }
+}
+
+// spheres that melt into each other when closer than `smoothness`
+pub struct Metaballs {
+    pub balls: Vec<Sphere>,
+    pub smoothness: f64,
+}
+
+impl DistanceField for Metaballs {
+    fn distance(&self, point: Vec3) -> f64 {
+        self.balls
+            .iter()
+            .map(|ball| ball.distance(point))
+            .reduce(|a, b| smooth_min(a, b, self.smoothness))
+            .unwrap_or(f64::INFINITY)
+    }
+
+    fn bounds(&self) -> Bounds {
+        let margin = self.smoothness / 4.0;
+        self.balls
+            .iter()
+            .map(|ball| Bounds::around(ball.center, ball.radius + margin))
+            .reduce(Bounds::union)
+            .unwrap_or(Bounds::around(Vec3(0.0, 0.0, 0.0), 0.0))
+    }
+}
+
+// raytracer primitive for a distance field, found by sphere tracing
+pub struct Sdf<F: DistanceField>(pub F);
+
+impl<F: DistanceField> Sdf<F> {
+    // gradient by central differences
+    fn normal(&self, point: Vec3) -> Vec3 {
+        let e = SURFACE_DISTANCE;
+        let d = |offset: Vec3| self.0.distance(point + offset) - self.0.distance(point - offset);
+
+        Vec3(
+            d(Vec3(e, 0.0, 0.0)),
+            d(Vec3(0.0, e, 0.0)),
+            d(Vec3(0.0, 0.0, e)),
+        )
+        .unit()
+    }
+
+    fn hit(&self, origin: Vec3, direction: Vec3, t: f64) -> Hit {
+        Hit {
+            t,
+            normal: self.normal(origin + t * direction),
+        }
+    }
+
+    // marches from t until the sign of the distance flips, or past t_max
+    fn march(&self, origin: Vec3, direction: Vec3, mut t: f64, t_max: f64) -> Option<f64> {
+        let speed = direction.length();
+        let inside = self.0.distance(origin + t * direction) < 0.0;
+
+        for _ in 0..MAX_STEPS {
+            let distance = self.0.distance(origin + t * direction);
+            if (distance < 0.0) != inside || distance.abs() < SURFACE_DISTANCE {
+                return Some(t);
+            }
+
+            t += distance.abs().max(SURFACE_DISTANCE) / speed;
+            if t > t_max {
+                return None;
+            }
+        }
+
+        None
+    }
+}
+
+impl<F: DistanceField> Primitive for Sdf<F> {
+    fn spans(&self, origin: Vec3, direction: Vec3) -> Vec<Span> {
+        let (near, far) = self.0.bounds().slabs(origin, direction);
+        let mut spans = Vec::new();
+        let mut t = near;
+
+        while t < far {
+            let enter = if self.0.distance(origin + t * direction) < 0.0 {
+                t
+            } else {
+                match self.march(origin, direction, t, far) {
+                    Some(enter) => enter,
+                    None => break,
+                }
+            };
+            // step off the surface before looking for the way out
+            let exit = self
+                .march(origin, direction, enter + 2.0 * SURFACE_DISTANCE, far)
+                .unwrap_or(far);
+
+            spans.push(Span {
+                enter: self.hit(origin, direction, enter),
+                exit: self.hit(origin, direction, exit),
+            });
+            t = exit + 2.0 * SURFACE_DISTANCE;
+        }
+
+        spans
+    }
+
+    fn bounds(&self) -> Bounds {
+        self.0.bounds()
+    }
+
+    fn intersect(&self, origin: Vec3, direction: Vec3, t_min: f64, t_max: f64) -> Option<Hit> {
+        let (near, far) = self.0.bounds().slabs(origin, direction);
+        let (start, end) = (near.max(t_min), far.min(t_max));
+        if start > end {
+            return None;
+        }
+
+        let t = self.march(origin, direction, start, end)?;
+        Some(self.hit(origin, direction, t))
+    }
+}
@@ -0,0 +1,151 @@
+use std::f64::consts::PI;
+
+use crate::{Color, Rgb, Vec3};
+
+// octaves of turbulence distorting marble veins and wood rings
+const DISTORTION_OCTAVES: u32 = 4;
+
+// Solid patterns defined everywhere in space, so shapes look carved out of them. Values run
+// from 0 to 1.
+#[derive(Clone, Copy)]
+pub enum Pattern {
+    // alternating cubes with sides of `size`, centered on multiples of it so that floors and
+    // walls at round coordinates cut through the middle of a cell instead of along its face
+    Checker { size: f64 },
+    // smooth gradient noise with features about `scale` apart
+    Noise { scale: f64 },
+    // noise octaves summed, each twice as fine and half as strong as the last
+    Turbulence { scale: f64, octaves: u32 },
+    // veins across the x axis `scale` apart, bent by turbulence
+    Marble { scale: f64, turbulence: f64 },
+    // rings around the y axis `scale` apart, bent by turbulence
+    Wood { scale: f64, turbulence: f64 },
+}
+
+impl Pattern {
+    pub fn value(&self, point: Vec3) -> f64 {
+        match *self {
+            Pattern::Checker { size } => {
+                // in floating point, as far away points have huge cell numbers
+                let cell = |c: f64| (c / size + 0.5).floor();
+                let parity = (cell(point.0) + cell(point.1) + cell(point.2)).rem_euclid(2.0);
+                if parity.is_finite() {
+                    parity
+                } else {
+                    0.0
+                }
+            }
+            Pattern::Noise { scale } => 0.5 + 0.5 * noise(point / scale),
+            Pattern::Turbulence { scale, octaves } => turbulence(point / scale, octaves),
+            Pattern::Marble {
+                scale,
+                turbulence: amount,
+            } => {
+                let p = point / scale;
+                let phase = p.0 + amount * turbulence(p, DISTORTION_OCTAVES);
+                0.5 + 0.5 * (phase * PI).sin()
+            }
+            Pattern::Wood {
+                scale,
+                turbulence: amount,
+            } => {
+                let p = point / scale;
+                let radius = (p.0 * p.0 + p.2 * p.2).sqrt();
+                (radius + amount * turbulence(p, DISTORTION_OCTAVES)).fract()
+            }
+        }
+    }
+}
+
+// pattern values mapped onto a blend from one color to another
+#[derive(Clone, Copy)]
+pub struct Texture {
+    pub pattern: Pattern,
+    pub from: Color,
+    pub to: Color,
+}
+
+impl Texture {
+    pub fn new(pattern: Pattern, from: Color, to: Color) -> Self {
+        Self { pattern, from, to }
+    }
+
+    pub fn color(&self, point: Vec3) -> Color {
+        let t = self.pattern.value(point);
+        (Rgb::from(self.from) * (1.0 - t) + Rgb::from(self.to) * t).to_color()
+    }
+}
+
+// Perlin's gradient noise, from about -1 to 1 and zero at integer points. Gradients come
+// from hashing the lattice coordinates, so there's no permutation table to set up.
+fn noise(p: Vec3) -> f64 {
+    let (x0, y0, z0) = (p.0.floor(), p.1.floor(), p.2.floor());
+    let (fx, fy, fz) = (p.0 - x0, p.1 - y0, p.2 - z0);
+    let (x0, y0, z0) = (x0 as i64, y0 as i64, z0 as i64);
+
+    let corner = |dx: i64, dy: i64, dz: i64| {
+        let gradient = gradient(
+            x0.wrapping_add(dx),
+            y0.wrapping_add(dy),
+            z0.wrapping_add(dz),
+        );
+        gradient.dot(Vec3(fx - dx as f64, fy - dy as f64, fz - dz as f64))
+    };
+    let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+    let (u, v, w) = (fade(fx), fade(fy), fade(fz));
+
+    lerp(
+        lerp(
+            lerp(corner(0, 0, 0), corner(1, 0, 0), u),
+            lerp(corner(0, 1, 0), corner(1, 1, 0), u),
+            v,
+        ),
+        lerp(
+            lerp(corner(0, 0, 1), corner(1, 0, 1), u),
+            lerp(corner(0, 1, 1), corner(1, 1, 1), u),
+            v,
+        ),
+        w,
+    )
+}
+
+fn turbulence(p: Vec3, octaves: u32) -> f64 {
+    let (mut sum, mut total, mut weight, mut p) = (0.0, 0.0, 1.0, p);
+    for _ in 0..octaves.max(1) {
+        sum += noise(p).abs() * weight;
+        total += weight;
+        weight *= 0.5;
+        p = p * 2.0;
+    }
+    (sum / total).min(1.0)
+}
+
+// smootherstep, so the noise has continuous second derivatives across cells
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+// one of the twelve directions to the edges of a cube, picked by hashing the lattice point
+fn gradient(x: i64, y: i64, z: i64) -> Vec3 {
+    let mut h = (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+        ^ (z as u64).wrapping_mul(0x1656_67B1_9E37_79F9);
+    h ^= h >> 29;
+    h = h.wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    h ^= h >> 32;
+
+    match h % 12 {
+        0 => Vec3(1.0, 1.0, 0.0),
+        1 => Vec3(-1.0, 1.0, 0.0),
+        2 => Vec3(1.0, -1.0, 0.0),
+        3 => Vec3(-1.0, -1.0, 0.0),
+        4 => Vec3(1.0, 0.0, 1.0),
+        5 => Vec3(-1.0, 0.0, 1.0),
+        6 => Vec3(1.0, 0.0, -1.0),
+        7 => Vec3(-1.0, 0.0, -1.0),
+        8 => Vec3(0.0, 1.0, 1.0),
+        9 => Vec3(0.0, -1.0, 1.0),
+        10 => Vec3(0.0, 1.0, -1.0),
+        _ => Vec3(0.0, -1.0, -1.0),
+    }
+}
@@ -0,0 +1,1507 @@
+// Renders named scenes offscreen and compares them against the reference images in
+// tests/golden. Run with UPDATE_GOLDEN=1 to (re)generate the references after an
+// intentional change; failures leave the actual image and an amplified diff in
+// target/golden.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use cgfs::{
+    write_ppm, Attenuation, AxisBox, Background, Canvas, Color, Cone, Csg, Cylinder, Disc,
+    DrawMode, EnvironmentMap, Image, Integrator, Light, Material, Metaballs, Model, Moving, Object,
+    OrientedBox, Pattern, Plane, Primitive, Rasterizer, RasterizerLight, Raytracer, Recording,
+    Renderer, Rgb, RoundBox, Sdf, ShadingModel, SmoothUnion, Sphere, Texture, Torus, Transform,
+    Vec3,
+};
+
+const SIZE: usize = 96;
+
+// below these the images are considered different
+const MIN_PSNR: f64 = 40.0;
+const MIN_SSIM: f64 = 0.98;
+
+fn load(path: &Path) -> Image {
+    let data = fs::read(path).unwrap_or_else(|error| panic!("{}: {error}", path.display()));
+    Image::parse_ppm(&data).unwrap_or_else(|error| panic!("{}: {error}", path.display()))
+}
+
+// through the same PPM bytes the references are written as
+fn from_canvas(canvas: &Canvas) -> Image {
+    let mut data = Vec::new();
+    write_ppm(canvas, &mut data).unwrap();
+    Image::parse_ppm(&data).unwrap()
+}
+
+fn save(image: &Image, path: &Path) {
+    let mut data = format!("P6\n{} {}\n255\n", image.width(), image.height()).into_bytes();
+    data.extend(pixels(image).flatten());
+    fs::write(path, data).unwrap();
+}
+
+// 8-bit channels, row by row
+fn pixels(image: &Image) -> impl Iterator<Item = [u8; 3]> + '_ {
+    (0..image.height())
+        .flat_map(move |y| (0..image.width()).map(move |x| image.pixel(x, y)))
+        .map(|p| [p.0, p.1, p.2].map(|c| (c * 255.0).round() as u8))
+}
+
+fn luminance(image: &Image) -> Vec<f64> {
+    pixels(image)
+        .map(|[r, g, b]| 0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64)
+        .collect()
+}
+
+struct Comparison {
+    max_delta: u8,
+    differing_pixels: usize,
+    psnr: f64,
+    ssim: f64,
+}
+
+fn compare(expected: &Image, actual: &Image) -> Comparison {
+    let mut max_delta = 0;
+    let mut differing_pixels = 0;
+    let mut squared_error = 0.0;
+
+    for (e, a) in pixels(expected).zip(pixels(actual)) {
+        let delta = (0..3).map(|c| e[c].abs_diff(a[c])).max().unwrap();
+        max_delta = max_delta.max(delta);
+        if delta > 0 {
+            differing_pixels += 1;
+        }
+        squared_error += (0..3)
+            .map(|c| (e[c] as f64 - a[c] as f64).powi(2))
+            .sum::<f64>();
+    }
+
+    let mse = squared_error / (expected.width() * expected.height() * 3) as f64;
+    let psnr = if mse == 0.0 {
+        f64::INFINITY
+    } else {
+        10.0 * (255.0 * 255.0 / mse).log10()
+    };
+
+    Comparison {
+        max_delta,
+        differing_pixels,
+        psnr,
+        ssim: ssim(expected, actual),
+    }
+}
+
+// mean structural similarity of the luminance over 8x8 windows
+fn ssim(expected: &Image, actual: &Image) -> f64 {
+    const WINDOW: usize = 8;
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+    let (x, y) = (luminance(expected), luminance(actual));
+    let mut total = 0.0;
+    let mut windows = 0;
+
+    for wy in (0..=expected.height() - WINDOW).step_by(WINDOW / 2) {
+        for wx in (0..=expected.width() - WINDOW).step_by(WINDOW / 2) {
+            let offsets: Vec<usize> = (0..WINDOW * WINDOW)
+                .map(|i| (wy + i / WINDOW) * expected.width() + wx + i % WINDOW)
+                .collect();
+            let n = offsets.len() as f64;
+
+            let mean_x = offsets.iter().map(|&i| x[i]).sum::<f64>() / n;
+            let mean_y = offsets.iter().map(|&i| y[i]).sum::<f64>() / n;
+            let (mut var_x, mut var_y, mut covariance) = (0.0, 0.0, 0.0);
+            for &i in &offsets {
+                var_x += (x[i] - mean_x).powi(2) / n;
+                var_y += (y[i] - mean_y).powi(2) / n;
+                covariance += (x[i] - mean_x) * (y[i] - mean_y) / n;
+            }
+
+            total += ((2.0 * mean_x * mean_y + C1) * (2.0 * covariance + C2))
+                / ((mean_x * mean_x + mean_y * mean_y + C1) * (var_x + var_y + C2));
+            windows += 1;
+        }
+    }
+
+    total / windows as f64
+}
+
+// per-channel differences, amplified so small ones are visible
+fn diff_image(expected: &Image, actual: &Image) -> Image {
+    let pixels = pixels(expected)
+        .zip(pixels(actual))
+        .map(|(e, a)| {
+            let [r, g, b] = [0, 1, 2].map(|c| e[c].abs_diff(a[c]).saturating_mul(8));
+            Rgb(r as f64, g as f64, b as f64) * (1.0 / 255.0)
+        })
+        .collect();
+    Image::new(expected.width(), expected.height(), pixels)
+}
+
+fn check(name: &str, canvas: &Canvas) {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let reference = root.join("tests/golden").join(format!("{name}.ppm"));
+    let actual = from_canvas(canvas);
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        let mut out = fs::File::create(&reference).unwrap();
+        write_ppm(canvas, &mut out).unwrap();
+        return;
+    }
+
+    assert!(
+        reference.exists(),
+        "missing reference {}, run with UPDATE_GOLDEN=1 to create it",
+        reference.display()
+    );
+    let expected = load(&reference);
+    assert_eq!(
+        (expected.width(), expected.height()),
+        (actual.width(), actual.height()),
+        "{name}: size differs from the reference"
+    );
+
+    let comparison = compare(&expected, &actual);
+    if comparison.psnr < MIN_PSNR || comparison.ssim < MIN_SSIM {
+        let out: PathBuf = root.join("target/golden");
+        fs::create_dir_all(&out).unwrap();
+        save(&actual, &out.join(format!("{name}.ppm")));
+        save(
+            &diff_image(&expected, &actual),
+            &out.join(format!("{name}.diff.ppm")),
+        );
+
+        panic!(
+            "{name}: {} pixels differ (max delta {}), PSNR {:.2} dB, SSIM {:.4}; see {}",
+            comparison.differing_pixels,
+            comparison.max_delta,
+            comparison.psnr,
+            comparison.ssim,
+            out.display()
+        );
+    }
+}
+
+// gives every object its own material
+fn scene(objects: Vec<(Box<dyn Primitive>, Material)>, lights: Vec<Light>) -> Raytracer {
+    let (shapes, materials): (Vec<_>, Vec<_>) = objects.into_iter().unzip();
+    let objects = shapes
+        .into_iter()
+        .enumerate()
+        .map(|(material, shape)| Object { shape, material })
+        .collect();
+
+    Raytracer::new(materials, objects, lights)
+}
+
+// the ground most scenes stand on
+fn floor() -> Plane {
+    Plane {
+        normal: Vec3(0.0, 1.0, 0.0),
+        distance: 1.0,
+    }
+}
+
+// an object with Phong highlights
+fn object(shape: impl Primitive + 'static, color: Color) -> (Box<dyn Primitive>, Material) {
+    (Box::new(shape), Material::new(color).with_specular(100.0))
+}
+
+// glossy, slightly reflective objects under an ambient, a point and a directional light
+fn showcase(objects: Vec<(Box<dyn Primitive>, Color)>) -> Raytracer {
+    let objects = objects
+        .into_iter()
+        .map(|(shape, color)| {
+            let material = Material::new(color)
+                .with_specular(100.0)
+                .with_reflective(0.1);
+            (shape, material)
+        })
+        .collect();
+    let lights = vec![
+        Light::Ambient {
+            intensity: Rgb::gray(0.2),
+        },
+        Light::Point {
+            position: Vec3(-2.0, 3.0, 1.0),
+            intensity: Rgb::gray(0.6),
+            attenuation: Attenuation::None,
+        },
+        Light::Directional {
+            direction: Vec3(1.0, 4.0, -2.0),
+            intensity: Rgb::gray(0.2),
+        },
+    ];
+
+    scene(objects, lights)
+}
+
+// renders a still, accumulating `passes` samples per pixel when the renderer is progressive
+fn render(renderer: &impl Renderer, passes: u32) -> Canvas {
+    let mut canvas = Canvas::headless(SIZE, SIZE);
+    canvas.set_passes(passes);
+    canvas.render_still(renderer);
+    canvas
+}
+
+#[test]
+fn raytracer_default() {
+    check("raytracer_default", &render(&Raytracer::default_scene(), 1));
+}
+
+#[test]
+fn raytracer_primitives() {
+    let raytracer = showcase(vec![
+        (Box::new(floor()), Color(200, 200, 200)),
+        (
+            Box::new(AxisBox {
+                min: Vec3(-2.4, -1.0, 5.5),
+                max: Vec3(-1.4, 0.0, 6.5),
+            }),
+            Color::RED,
+        ),
+        (
+            Box::new(OrientedBox::new(
+                Vec3(-0.4, -0.4, 5.0),
+                Vec3(0.5, 0.6, 0.5),
+                Vec3(1.0, 0.3, -1.0),
+                Vec3(0.0, 1.0, 0.0),
+            )),
+            Color::GREEN,
+        ),
+        (
+            Box::new(Cylinder {
+                base: Vec3(0.9, -1.0, 5.0),
+                axis: Vec3(0.0, 1.0, 0.0),
+                height: 1.5,
+                radius: 0.5,
+            }),
+            Color::BLUE,
+        ),
+        (
+            Box::new(Cone {
+                base: Vec3(2.2, -1.0, 5.0),
+                axis: Vec3(0.0, 1.0, 0.0),
+                height: 1.6,
+                radius: 0.6,
+            }),
+            Color::YELLOW,
+        ),
+        (
+            Box::new(Disc {
+                center: Vec3(0.0, 1.3, 6.0),
+                normal: Vec3(0.0, -0.3, -1.0),
+                radius: 0.8,
+            }),
+            Color::PURPLE,
+        ),
+        (
+            Box::new(Sphere {
+                center: Vec3(-1.6, 1.0, 6.0),
+                radius: 0.5,
+            }),
+            Color::CYAN,
+        ),
+    ]);
+
+    check("raytracer_primitives", &render(&raytracer, 1));
+}
+
+#[test]
+fn raytracer_csg() {
+    let lens = Csg::intersection(
+        Sphere {
+            center: Vec3(-2.2, 0.0, 5.5),
+            radius: 1.0,
+        },
+        Sphere {
+            center: Vec3(-1.0, 0.0, 5.5),
+            radius: 1.0,
+        },
+    );
+    let drilled_cube = Csg::difference(
+        OrientedBox::new(
+            Vec3(0.8, 0.0, 6.0),
+            Vec3(0.8, 0.8, 0.8),
+            Vec3(1.0, 0.0, -0.6),
+            Vec3(0.0, 1.0, 0.3),
+        ),
+        Cylinder {
+            base: Vec3(0.8, 0.0, 3.0),
+            axis: Vec3(0.0, 0.0, 1.0),
+            height: 6.0,
+            radius: 0.45,
+        },
+    );
+    let bitten_sphere = Csg::difference(
+        Sphere {
+            center: Vec3(0.0, 1.6, 7.0),
+            radius: 0.8,
+        },
+        Sphere {
+            center: Vec3(-0.6, 1.9, 6.4),
+            radius: 0.6,
+        },
+    );
+    let ice_cream = Csg::union(
+        Sphere {
+            center: Vec3(2.4, 0.5, 6.5),
+            radius: 0.5,
+        },
+        Cone {
+            base: Vec3(2.4, 0.5, 6.5),
+            axis: Vec3(0.0, -1.0, 0.0),
+            height: 1.5,
+            radius: 0.45,
+        },
+    );
+
+    let raytracer = showcase(vec![
+        (Box::new(lens), Color::CYAN),
+        (Box::new(drilled_cube), Color::RED),
+        (Box::new(bitten_sphere), Color::GREEN),
+        (Box::new(ice_cream), Color::YELLOW),
+    ]);
+
+    check("raytracer_csg", &render(&raytracer, 1));
+}
+
+#[test]
+fn raytracer_sdf() {
+    let raytracer = showcase(vec![
+        (Box::new(floor()), Color(200, 200, 200)),
+        (
+            Box::new(Sdf(Torus {
+                center: Vec3(-1.6, -0.6, 5.0),
+                major_radius: 0.7,
+                minor_radius: 0.25,
+            })),
+            Color::RED,
+        ),
+        (
+            Box::new(Sdf(RoundBox {
+                center: Vec3(0.0, -0.4, 5.5),
+                half_size: Vec3(0.6, 0.6, 0.6),
+                radius: 0.2,
+            })),
+            Color::GREEN,
+        ),
+        (
+            Box::new(Sdf(Metaballs {
+                balls: vec![
+                    Sphere {
+                        center: Vec3(1.4, -0.5, 5.0),
+                        radius: 0.45,
+                    },
+                    Sphere {
+                        center: Vec3(2.0, -0.3, 5.2),
+                        radius: 0.35,
+                    },
+                    Sphere {
+                        center: Vec3(1.7, 0.2, 5.1),
+                        radius: 0.3,
+                    },
+                ],
+                smoothness: 0.5,
+            })),
+            Color::BLUE,
+        ),
+        (
+            Box::new(Sdf(SmoothUnion {
+                left: Box::new(Torus {
+                    center: Vec3(-0.5, 1.2, 6.0),
+                    major_radius: 0.6,
+                    minor_radius: 0.15,
+                }),
+                right: Box::new(Sphere {
+                    center: Vec3(-0.5, 1.2, 6.0),
+                    radius: 0.35,
+                }),
+                smoothness: 0.6,
+            })),
+            Color::PURPLE,
+        ),
+        (
+            Box::new(Sphere {
+                center: Vec3(1.2, 1.2, 6.0),
+                radius: 0.5,
+            }),
+            Color::CYAN,
+        ),
+    ]);
+
+    check("raytracer_sdf", &render(&raytracer, 1));
+}
+
+#[test]
+fn raytracer_area_lights() {
+    let raytracer = scene(
+        vec![
+            object(floor(), Color(200, 200, 200)),
+            object(
+                Sphere {
+                    center: Vec3(-1.2, -0.4, 4.0),
+                    radius: 0.6,
+                },
+                Color(220, 80, 80),
+            ),
+            object(
+                AxisBox {
+                    min: Vec3(0.6, -1.0, 3.4),
+                    max: Vec3(1.6, 0.0, 4.4),
+                },
+                Color(80, 120, 220),
+            ),
+        ],
+        vec![
+            Light::Ambient {
+                intensity: Rgb::gray(0.1),
+            },
+            Light::Rect {
+                corner: Vec3(-2.5, 2.0, 3.0),
+                edges: (Vec3(1.0, 0.0, 0.0), Vec3(0.0, 0.0, 1.5)),
+                intensity: Rgb::gray(0.3),
+                samples: 16,
+                attenuation: Attenuation::None,
+            },
+            Light::Disc {
+                center: Vec3(2.0, 2.5, 3.0),
+                normal: Vec3(0.0, -1.0, 0.0),
+                radius: 0.8,
+                intensity: Rgb::gray(0.15),
+                samples: 16,
+                attenuation: Attenuation::None,
+            },
+            Light::Sphere {
+                center: Vec3(0.0, 1.5, 6.0),
+                radius: 0.5,
+                intensity: Rgb::gray(0.4),
+                samples: 8,
+                attenuation: Attenuation::None,
+            },
+        ],
+    );
+
+    check("raytracer_area_lights", &render(&raytracer, 1));
+}
+
+#[test]
+fn raytracer_spotlights() {
+    let raytracer = scene(
+        vec![
+            object(floor(), Color(200, 200, 200)),
+            object(
+                Plane {
+                    normal: Vec3(0.0, 0.0, -1.0),
+                    distance: 6.0,
+                },
+                Color(200, 200, 200),
+            ),
+            object(
+                Sphere {
+                    center: Vec3(0.0, -0.4, 4.0),
+                    radius: 0.6,
+                },
+                Color(220, 80, 80),
+            ),
+        ],
+        vec![
+            Light::Ambient {
+                intensity: Rgb::gray(0.1),
+            },
+            Light::Spot {
+                position: Vec3(-1.5, 2.0, 2.0),
+                direction: Vec3(1.0, -2.0, 2.0),
+                inner: 15.0,
+                outer: 25.0,
+                intensity: Rgb::gray(0.8),
+                attenuation: Attenuation::None,
+            },
+            // hard-edged when both angles match
+            Light::Spot {
+                position: Vec3(2.0, 1.5, 3.0),
+                direction: Vec3(0.0, -0.3, 1.0),
+                inner: 12.0,
+                outer: 12.0,
+                intensity: Rgb::gray(0.6),
+                attenuation: Attenuation::None,
+            },
+        ],
+    );
+
+    check("raytracer_spotlights", &render(&raytracer, 1));
+}
+
+#[test]
+fn raytracer_colored_lights() {
+    let color = Color(230, 230, 230);
+
+    let raytracer = scene(
+        vec![
+            object(floor(), color),
+            object(
+                Plane {
+                    normal: Vec3(0.0, 0.0, -1.0),
+                    distance: 7.0,
+                },
+                color,
+            ),
+            object(
+                Sphere {
+                    center: Vec3(0.0, -0.3, 4.5),
+                    radius: 0.7,
+                },
+                color,
+            ),
+        ],
+        vec![
+            Light::Ambient {
+                intensity: Rgb(0.05, 0.05, 0.1),
+            },
+            Light::Point {
+                position: Vec3(-1.5, 0.0, 3.5),
+                intensity: Rgb(1.5, 0.3, 0.2),
+                attenuation: Attenuation::InverseSquare { range: 5.0 },
+            },
+            Light::Point {
+                position: Vec3(1.5, 0.5, 4.0),
+                intensity: Rgb(0.2, 0.5, 1.0),
+                attenuation: Attenuation::Polynomial {
+                    constant: 1.0,
+                    linear: 0.2,
+                    quadratic: 0.3,
+                },
+            },
+            Light::Spot {
+                position: Vec3(0.0, 3.0, 4.5),
+                direction: Vec3(0.0, -1.0, 0.0),
+                inner: 10.0,
+                outer: 20.0,
+                intensity: Rgb(0.6, 0.6, 0.2),
+                attenuation: Attenuation::None,
+            },
+        ],
+    );
+
+    check("raytracer_colored_lights", &render(&raytracer, 1));
+}
+
+#[test]
+fn raytracer_materials() {
+    // spheres share materials by index
+    let materials = vec![
+        Material::new(Color(200, 200, 200)).with_specular(10.0),
+        Material::new(Color(60, 120, 220))
+            .with_specular(200.0)
+            .with_transparency(0.6),
+        Material::new(Color(220, 120, 40)).with_emission(Rgb(0.6, 0.3, 0.1)),
+        Material::new(Color(220, 220, 220))
+            .with_specular(500.0)
+            .with_reflective(0.8),
+    ];
+    let sphere = |x, z, material| Object {
+        shape: Box::new(Sphere {
+            center: Vec3(x, -0.4, z),
+            radius: 0.6,
+        }),
+        material,
+    };
+
+    let raytracer = Raytracer::new(
+        materials,
+        vec![
+            Object {
+                shape: Box::new(floor()),
+                material: 0,
+            },
+            sphere(-1.4, 4.0, 1),
+            sphere(0.0, 5.0, 2),
+            sphere(1.4, 4.0, 3),
+            sphere(0.0, 3.2, 1),
+        ],
+        vec![
+            Light::Ambient {
+                intensity: Rgb::gray(0.2),
+            },
+            Light::Point {
+                position: Vec3(2.0, 2.0, 1.0),
+                intensity: Rgb::gray(0.7),
+                attenuation: Attenuation::None,
+            },
+        ],
+    );
+
+    check("raytracer_materials", &render(&raytracer, 1));
+}
+
+// roughness increases to the right, the back row is metallic
+fn pbr_spheres() -> Raytracer {
+    let mut materials = vec![Material::new(Color(120, 120, 120))];
+    let mut objects = vec![Object {
+        shape: Box::new(floor()),
+        material: 0,
+    }];
+    for row in 0..2 {
+        for column in 0..4 {
+            let (metallic, roughness) = (row as f64, 0.15 + column as f64 * 0.28);
+            materials.push(
+                Material::new(Color(230, 160, 60)).with_metallic_roughness(metallic, roughness),
+            );
+            objects.push(Object {
+                shape: Box::new(Sphere {
+                    center: Vec3(column as f64 * 1.1 - 1.65, -0.55 + row as f64, 4.0),
+                    radius: 0.45,
+                }),
+                material: materials.len() - 1,
+            });
+        }
+    }
+
+    Raytracer::new(
+        materials,
+        objects,
+        vec![
+            Light::Ambient {
+                intensity: Rgb::gray(0.1),
+            },
+            Light::Point {
+                position: Vec3(-2.0, 3.0, 1.0),
+                intensity: Rgb::gray(0.9),
+                attenuation: Attenuation::None,
+            },
+            Light::Directional {
+                direction: Vec3(1.0, 1.0, -1.0),
+                intensity: Rgb::gray(0.3),
+            },
+        ],
+    )
+}
+
+#[test]
+fn raytracer_pbr() {
+    check("raytracer_pbr", &render(&pbr_spheres(), 1));
+}
+
+// the same spheres path traced, so highlights and metals have to come from sampling the BRDF
+#[test]
+fn raytracer_path_traced_pbr() {
+    let raytracer = pbr_spheres().with_integrator(Integrator::PathTracing { max_depth: 4 });
+
+    check("raytracer_path_traced_pbr", &render(&raytracer, 32));
+}
+
+// every pattern as albedo, plus masks turning reflections and highlights on and off
+#[test]
+fn raytracer_textures() {
+    let sphere = |x, y, z| -> Box<dyn Primitive> {
+        Box::new(Sphere {
+            center: Vec3(x, y, z),
+            radius: 0.55,
+        })
+    };
+    let textured = |pattern, from, to| {
+        Material::new(Color::BLACK)
+            .with_specular(100.0)
+            .with_albedo_texture(Texture::new(pattern, from, to))
+    };
+
+    let raytracer = scene(
+        vec![
+            (
+                Box::new(floor()),
+                Material::new(Color(200, 200, 200))
+                    .with_reflective(0.6)
+                    .with_reflective_mask(Pattern::Checker { size: 0.5 }),
+            ),
+            (
+                sphere(-1.3, 0.5, 4.0),
+                textured(
+                    Pattern::Marble {
+                        scale: 0.3,
+                        turbulence: 2.0,
+                    },
+                    Color(60, 60, 70),
+                    Color(235, 235, 225),
+                ),
+            ),
+            (
+                sphere(0.0, 0.5, 4.0),
+                textured(
+                    Pattern::Wood {
+                        scale: 0.15,
+                        turbulence: 0.4,
+                    },
+                    Color(110, 60, 20),
+                    Color(200, 140, 70),
+                ),
+            ),
+            (
+                sphere(1.3, 0.5, 4.0),
+                textured(Pattern::Noise { scale: 0.15 }, Color::BLUE, Color::YELLOW),
+            ),
+            (
+                sphere(-0.65, -0.45, 3.5),
+                textured(
+                    Pattern::Turbulence {
+                        scale: 0.3,
+                        octaves: 5,
+                    },
+                    Color(30, 90, 30),
+                    Color(220, 240, 200),
+                ),
+            ),
+            (
+                sphere(0.65, -0.45, 3.5),
+                Material::new(Color(200, 60, 60))
+                    .with_specular(50.0)
+                    .with_specular_mask(Pattern::Checker { size: 0.2 }),
+            ),
+        ],
+        vec![
+            Light::Ambient {
+                intensity: Rgb::gray(0.2),
+            },
+            Light::Point {
+                position: Vec3(2.0, 3.0, 1.0),
+                intensity: Rgb::gray(0.8),
+                attenuation: Attenuation::None,
+            },
+        ],
+    )
+    .with_background(Background::Solid(Rgb(0.5, 0.7, 1.0)));
+    check("raytracer_textures", &render(&raytracer, 1));
+}
+
+// checkerboard round the horizon over a sky gradient, written out and parsed back as a P6 file
+fn equirectangular_checker() -> Image {
+    let (width, height) = (64, 32);
+    let mut ppm = format!("P6\n# checker\n{width} {height}\n255\n").into_bytes();
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = if (x / 8 + y / 8) % 2 == 0 {
+                [230, 230, 240]
+            } else {
+                [40 + y as u8 * 4, 60, 140]
+            };
+            ppm.extend_from_slice(&pixel);
+        }
+    }
+    Image::parse_ppm(&ppm).unwrap()
+}
+
+fn mirror_spheres(background: Background) -> Raytracer {
+    let materials = vec![
+        Material::new(Color(200, 200, 200))
+            .with_specular(500.0)
+            .with_reflective(0.9),
+        Material::new(Color(200, 80, 80)).with_specular(50.0),
+    ];
+    let sphere = |x, z, radius, material| Object {
+        shape: Box::new(Sphere {
+            center: Vec3(x, 0.0, z),
+            radius,
+        }),
+        material,
+    };
+
+    Raytracer::new(
+        materials,
+        vec![sphere(-0.8, 4.0, 0.8, 0), sphere(1.0, 3.5, 0.5, 1)],
+        vec![
+            Light::Ambient {
+                intensity: Rgb::gray(0.3),
+            },
+            Light::Directional {
+                direction: Vec3(1.0, 2.0, -1.0),
+                intensity: Rgb::gray(0.6),
+            },
+        ],
+    )
+    .with_background(background)
+}
+
+#[test]
+fn raytracer_equirectangular() {
+    let raytracer = mirror_spheres(Background::Equirectangular(equirectangular_checker()));
+    check("raytracer_equirectangular", &render(&raytracer, 1));
+}
+
+#[test]
+fn raytracer_cube_map() {
+    // every face gets its own tint, darker towards its bottom row, as plain P3 files
+    let face = |r, g, b| {
+        let ppm = format!(
+            "P3 2 2 255\n{r} {g} {b} {r} {g} {b}\n{} {} {} 0 0 0\n",
+            r / 2,
+            g / 2,
+            b / 2
+        );
+        Image::parse_ppm(ppm.as_bytes()).unwrap()
+    };
+    let faces = [
+        face(255, 80, 80),
+        face(80, 255, 80),
+        face(200, 200, 255),
+        face(120, 90, 60),
+        face(255, 255, 80),
+        face(80, 255, 255),
+    ];
+
+    let raytracer = mirror_spheres(Background::CubeMap(Box::new(faces)));
+    check("raytracer_cube_map", &render(&raytracer, 1));
+}
+
+#[test]
+fn rasterizer_gradient_background() {
+    let rasterizer = Rasterizer::default_scene().with_background(Background::Gradient {
+        bottom: Rgb(0.35, 0.3, 0.25),
+        top: Rgb(0.4, 0.6, 1.0),
+    });
+    check("rasterizer_gradient_background", &render(&rasterizer, 1));
+}
+
+// Radiance file of a sky above brown ground with a small bright sun behind the camera,
+// scanlines run-length encoded
+fn hdr_sky() -> Vec<u8> {
+    let (width, height) = (64, 32);
+    let mut hdr =
+        format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {height} +X {width}\n").into_bytes();
+    for y in 0..height {
+        let scanline: Vec<[u8; 4]> = (0..width)
+            .map(|x| {
+                let radiance = if (5..7).contains(&x) && (8..10).contains(&y) {
+                    Rgb(60.0, 55.0, 45.0)
+                } else if y < height / 2 {
+                    Rgb(0.3, 0.5, 0.9) * (0.6 + y as f64 / height as f64)
+                } else {
+                    Rgb(0.35, 0.3, 0.25)
+                };
+                to_rgbe(radiance)
+            })
+            .collect();
+
+        hdr.extend_from_slice(&[2, 2, 0, width as u8]);
+        for channel in 0..4 {
+            let values: Vec<u8> = scanline.iter().map(|p| p[channel]).collect();
+            let mut x = 0;
+            while x < width {
+                let run = values[x..].iter().take_while(|v| **v == values[x]).count();
+                if run >= 3 {
+                    let run = run.min(127);
+                    hdr.extend_from_slice(&[128 + run as u8, values[x]]);
+                    x += run;
+                } else {
+                    let literal = (x + 1..width)
+                        .find(|&i| values[i..].iter().take_while(|v| **v == values[i]).count() >= 3)
+                        .unwrap_or(width)
+                        .min(x + 128)
+                        - x;
+                    hdr.push(literal as u8);
+                    hdr.extend_from_slice(&values[x..x + literal]);
+                    x += literal;
+                }
+            }
+        }
+    }
+    hdr
+}
+
+fn to_rgbe(color: Rgb) -> [u8; 4] {
+    let max = color.max_component();
+    if max < 1e-32 {
+        return [0; 4];
+    }
+    let exponent = max.log2().floor() as i32 + 1;
+    let scale = 256.0 / 2f64.powi(exponent);
+    [
+        (color.0 * scale) as u8,
+        (color.1 * scale) as u8,
+        (color.2 * scale) as u8,
+        (exponent + 128) as u8,
+    ]
+}
+
+#[test]
+fn raytracer_environment() {
+    let map = Arc::new(EnvironmentMap::new(Image::parse_hdr(&hdr_sky()).unwrap()));
+    let sphere = |x, radius| -> Box<dyn Primitive> {
+        Box::new(Sphere {
+            center: Vec3(x, radius - 1.0, 4.0),
+            radius,
+        })
+    };
+
+    let raytracer = scene(
+        vec![
+            (Box::new(floor()), Material::new(Color(180, 180, 180))),
+            (sphere(-1.3, 0.6), Material::new(Color(220, 220, 220))),
+            (
+                sphere(0.0, 0.6),
+                Material::new(Color(230, 180, 90)).with_metallic_roughness(1.0, 0.3),
+            ),
+            (
+                sphere(1.3, 0.6),
+                Material::new(Color(200, 60, 60)).with_specular(50.0),
+            ),
+        ],
+        vec![Light::Environment {
+            map: map.clone(),
+            intensity: Rgb::WHITE,
+            samples: 64,
+        }],
+    )
+    .with_background(Background::Equirectangular(map.image().clone()));
+    check("raytracer_environment", &render(&raytracer, 1));
+}
+
+#[test]
+fn rasterizer_environment() {
+    let map = Arc::new(EnvironmentMap::new(Image::parse_hdr(&hdr_sky()).unwrap()));
+    let materials = [
+        Color::RED,
+        Color::GREEN,
+        Color::BLUE,
+        Color::YELLOW,
+        Color::PURPLE,
+        Color::CYAN,
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(i, color)| match i {
+        0 | 1 => Material::new(color).with_specular(50.0),
+        _ => Material::new(color).with_metallic_roughness(i as f64 % 2.0, 0.1 + 0.2 * i as f64),
+    })
+    .collect();
+
+    let rasterizer = Rasterizer::default_scene()
+        .with_shading_model(ShadingModel::Phong)
+        .with_materials(materials)
+        .with_lights(vec![RasterizerLight::Environment {
+            map: map.clone(),
+            intensity: Rgb::gray(0.6),
+        }])
+        .with_background(Background::Equirectangular(map.image().clone()));
+    check("rasterizer_environment", &render(&rasterizer, 1));
+}
+
+#[test]
+fn raytracer_path_tracing() {
+    let wall = |normal, distance, color| object(Plane { normal, distance }, color);
+
+    // a Cornell box lit by an emissive panel and a dim point light just below it
+    let raytracer = scene(
+        vec![
+            wall(Vec3(0.0, 1.0, 0.0), 1.0, Color(200, 200, 200)),
+            wall(Vec3(0.0, -1.0, 0.0), 1.0, Color(200, 200, 200)),
+            wall(Vec3(0.0, 0.0, -1.0), 3.5, Color(200, 200, 200)),
+            wall(Vec3(1.0, 0.0, 0.0), 1.0, Color(200, 40, 40)),
+            wall(Vec3(-1.0, 0.0, 0.0), 1.0, Color(40, 200, 40)),
+            (
+                Box::new(Sphere {
+                    center: Vec3(-0.45, -0.6, 2.6),
+                    radius: 0.4,
+                }),
+                Material::new(Color(230, 230, 230)).with_reflective(0.9),
+            ),
+            object(
+                AxisBox {
+                    min: Vec3(0.1, -1.0, 2.0),
+                    max: Vec3(0.7, -0.3, 2.6),
+                },
+                Color(200, 200, 200),
+            ),
+            (
+                Box::new(Disc {
+                    center: Vec3(0.0, 0.99, 2.5),
+                    normal: Vec3(0.0, -1.0, 0.0),
+                    radius: 0.35,
+                }),
+                Material::new(Color(0, 0, 0)).with_emission(Rgb(4.0, 3.8, 3.4)),
+            ),
+        ],
+        vec![Light::Point {
+            position: Vec3(0.0, 0.9, 2.5),
+            intensity: Rgb::gray(0.3),
+            attenuation: Attenuation::None,
+        }],
+    )
+    .with_integrator(Integrator::PathTracing { max_depth: 8 });
+
+    check("raytracer_path_tracing", &render(&raytracer, 32));
+}
+
+// spheres at three depths with the focus picked on the middle one, as a right click would
+#[test]
+fn raytracer_depth_of_field() {
+    let sphere = |center| -> Box<dyn Primitive> {
+        Box::new(Sphere {
+            center,
+            radius: 0.7,
+        })
+    };
+
+    let mut raytracer = showcase(vec![
+        (Box::new(floor()), Color(200, 200, 200)),
+        (sphere(Vec3(-1.0, -0.3, 2.5)), Color::RED),
+        (sphere(Vec3(0.0, -0.3, 4.5)), Color::GREEN),
+        (sphere(Vec3(1.8, -0.3, 8.0)), Color::BLUE),
+    ])
+    .with_depth_of_field(0.12, 1.0);
+
+    let canvas = Canvas::headless(SIZE, SIZE);
+    assert!(raytracer.focus(&canvas, 0, -3));
+    assert!(!raytracer.focus(&canvas, 0, SIZE as i32 / 2 - 1));
+    check("raytracer_depth_of_field", &render(&raytracer, 32));
+}
+
+// a box turning on a turntable and a sphere sliding past a still one, over the whole shutter
+#[test]
+fn raytracer_motion_blur() {
+    let at = |x, y, z, yaw| Transform {
+        translation: Vec3(x, y, z),
+        yaw,
+        scale: 1.0,
+    };
+
+    let raytracer = showcase(vec![
+        (Box::new(floor()), Color(200, 200, 200)),
+        (
+            Box::new(
+                Moving::new(
+                    OrientedBox::new(
+                        Vec3(0.0, 0.0, 0.0),
+                        Vec3(0.7, 0.5, 0.2),
+                        Vec3(1.0, 0.0, 0.0),
+                        Vec3(0.0, 1.0, 0.0),
+                    ),
+                    at(-0.9, -0.5, 4.0, 0.0),
+                    at(-0.9, -0.5, 4.0, 60.0),
+                )
+                .unwrap(),
+            ),
+            Color::YELLOW,
+        ),
+        (
+            Box::new(
+                Moving::new(
+                    Sphere {
+                        center: Vec3(0.0, 0.0, 0.0),
+                        radius: 0.4,
+                    },
+                    at(0.2, 0.2, 3.5, 0.0),
+                    at(1.2, 0.2, 3.5, 0.0),
+                )
+                .unwrap(),
+            ),
+            Color::RED,
+        ),
+        (
+            Box::new(Sphere {
+                center: Vec3(1.0, -0.6, 4.5),
+                radius: 0.4,
+            }),
+            Color::BLUE,
+        ),
+    ])
+    .with_shutter(0.0, 1.0);
+
+    // shrinking to nothing would leave rays with no local space to go to
+    let vanishing = Transform {
+        scale: 0.0,
+        ..Transform::IDENTITY
+    };
+    assert!(Moving::new(floor(), Transform::IDENTITY, vanishing).is_none());
+
+    check("raytracer_motion_blur", &render(&raytracer, 32));
+}
+
+#[test]
+fn rasterizer_flat() {
+    let rasterizer = Rasterizer::default_scene().with_shading_model(ShadingModel::Flat);
+    check("rasterizer_flat", &render(&rasterizer, 1));
+}
+
+#[test]
+fn rasterizer_gouraud() {
+    let rasterizer = Rasterizer::default_scene().with_shading_model(ShadingModel::Gouraud);
+    check("rasterizer_gouraud", &render(&rasterizer, 1));
+}
+
+#[test]
+fn rasterizer_phong() {
+    let rasterizer = Rasterizer::default_scene().with_shading_model(ShadingModel::Phong);
+    check("rasterizer_phong", &render(&rasterizer, 1));
+}
+
+#[test]
+fn rasterizer_spotlight() {
+    let rasterizer = Rasterizer::default_scene().with_lights(vec![
+        RasterizerLight::Ambient {
+            intensity: Rgb::gray(0.1),
+        },
+        RasterizerLight::Spot {
+            position: Vec3(-3.0, 3.0, 2.0),
+            direction: Vec3(4.75, -3.5, 5.0),
+            inner: 6.0,
+            outer: 14.0,
+            intensity: Rgb::gray(0.9),
+            attenuation: Attenuation::None,
+        },
+    ]);
+    check("rasterizer_spotlight", &render(&rasterizer, 1));
+}
+
+#[test]
+fn rasterizer_colored_lights() {
+    let rasterizer = Rasterizer::default_scene()
+        .with_shading_model(ShadingModel::Gouraud)
+        .with_lights(vec![
+            RasterizerLight::Ambient {
+                intensity: Rgb(0.1, 0.1, 0.15),
+            },
+            RasterizerLight::Point {
+                position: Vec3(-3.0, 2.0, 5.0),
+                intensity: Rgb(12.0, 4.0, 2.0),
+                attenuation: Attenuation::InverseSquare { range: 12.0 },
+            },
+            RasterizerLight::Directional {
+                direction: Vec3(1.0, 1.0, -1.0),
+                intensity: Rgb(0.1, 0.2, 0.5),
+            },
+        ]);
+    check("rasterizer_colored_lights", &render(&rasterizer, 1));
+}
+
+#[test]
+fn rasterizer_pbr() {
+    let materials = [
+        Color::RED,
+        Color::GREEN,
+        Color::BLUE,
+        Color::YELLOW,
+        Color::PURPLE,
+        Color::CYAN,
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(i, color)| Material::new(color).with_metallic_roughness(i as f64 % 2.0, 0.3))
+    .collect();
+
+    let rasterizer = Rasterizer::default_scene()
+        .with_shading_model(ShadingModel::Phong)
+        .with_materials(materials);
+    check("rasterizer_pbr", &render(&rasterizer, 1));
+}
+
+#[test]
+fn rasterizer_textures() {
+    let marble = Pattern::Marble {
+        scale: 0.4,
+        turbulence: 2.0,
+    };
+    let wood = Pattern::Wood {
+        scale: 0.2,
+        turbulence: 0.4,
+    };
+    let materials = vec![
+        Material::new(Color::RED).with_albedo_texture(Texture::new(
+            marble,
+            Color(120, 20, 20),
+            Color(250, 220, 220),
+        )),
+        Material::new(Color::GREEN)
+            .with_specular(50.0)
+            .with_albedo_texture(Texture::new(
+                Pattern::Checker { size: 0.5 },
+                Color::GREEN,
+                Color(20, 60, 20),
+            ))
+            .with_specular_mask(Pattern::Checker { size: 0.5 }),
+        Material::new(Color::BLUE).with_albedo_texture(Texture::new(
+            wood,
+            Color(110, 60, 20),
+            Color(200, 140, 70),
+        )),
+        Material::new(Color::YELLOW).with_albedo_texture(Texture::new(
+            Pattern::Noise { scale: 0.3 },
+            Color::YELLOW,
+            Color::PURPLE,
+        )),
+        Material::new(Color::PURPLE).with_specular(50.0),
+        Material::new(Color::CYAN).with_specular(50.0),
+    ];
+
+    let rasterizer = Rasterizer::default_scene()
+        .with_shading_model(ShadingModel::Phong)
+        .with_materials(materials);
+    check("rasterizer_textures", &render(&rasterizer, 1));
+}
+
+// camera moves forward, turns and looks down, exercising clipping along the way
+#[test]
+fn rasterizer_walk() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let recording = Recording::load(root.join("tests/golden/walk.rec")).unwrap();
+
+    let mut rasterizer = Rasterizer::default_scene();
+    let mut canvas = Canvas::headless(SIZE, SIZE);
+    canvas.replay(&mut rasterizer, &recording, None).unwrap();
+
+    check("rasterizer_walk", &canvas);
+}
+
+#[test]
+fn raytracer_walk() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let recording = Recording::load(root.join("tests/golden/walk.rec")).unwrap();
+
+    let mut raytracer = Raytracer::default_scene();
+    let mut canvas = Canvas::headless(SIZE, SIZE);
+    canvas.replay(&mut raytracer, &recording, None).unwrap();
+
+    check("raytracer_walk", &canvas);
+}
+
+// a 4x4 grid of hemispherical dimples as a tangent space normal map
+fn dimpled_normals() -> Arc<Image> {
+    let size = 64;
+    let pixels = (0..size * size)
+        .map(|i| {
+            let cell = |c: usize| (c % 16) as f64 / 8.0 - 15.0 / 16.0;
+            // image rows grow downwards but tangent space y points up
+            let (x, y) = (cell(i % size), -cell(i / size));
+            let r2 = x * x + y * y;
+            let normal = if r2 < 0.7 {
+                let z = (1.0 - r2).sqrt();
+                Vec3(-x, -y, z)
+            } else {
+                Vec3(0.0, 0.0, 1.0)
+            };
+            Rgb(normal.0 + 1.0, normal.1 + 1.0, normal.2 + 1.0) * 0.5
+        })
+        .collect();
+    Arc::new(Image::new(size, size, pixels))
+}
+
+// running bond bricks, 4 courses high, with sunken mortar
+fn brick_heights() -> Arc<Image> {
+    let size = 64;
+    let pixels = (0..size * size)
+        .map(|i| {
+            let (x, y) = (i % size, i / size);
+            let offset = if (y / 16) % 2 == 0 { 0 } else { 16 };
+            let mortar = y % 16 < 2 || (x + offset) % 32 < 2;
+            Rgb::gray(if mortar { 0.0 } else { 1.0 })
+        })
+        .collect();
+    Arc::new(Image::new(size, size, pixels))
+}
+
+#[test]
+fn raytracer_normal_maps() {
+    let sphere = |x, y, z, radius| -> Box<dyn Primitive> {
+        Box::new(Sphere {
+            center: Vec3(x, y, z),
+            radius,
+        })
+    };
+
+    let raytracer = scene(
+        vec![
+            (
+                Box::new(floor()),
+                Material::new(Color(190, 90, 60))
+                    .with_specular(20.0)
+                    .with_bump_map(brick_heights(), 2.0),
+            ),
+            (
+                sphere(-0.8, 0.0, 4.0, 0.9),
+                Material::new(Color(200, 200, 210))
+                    .with_specular(200.0)
+                    .with_normal_map(dimpled_normals()),
+            ),
+            (
+                sphere(1.0, -0.2, 3.5, 0.7),
+                Material::new(Color(80, 140, 200))
+                    .with_specular(50.0)
+                    .with_bump_map(brick_heights(), 4.0),
+            ),
+        ],
+        vec![
+            Light::Ambient {
+                intensity: Rgb::gray(0.2),
+            },
+            Light::Point {
+                position: Vec3(-2.0, 3.0, 1.0),
+                intensity: Rgb::gray(0.8),
+                attenuation: Attenuation::None,
+            },
+        ],
+    )
+    .with_background(Background::Solid(Rgb(0.5, 0.7, 1.0)));
+    check("raytracer_normal_maps", &render(&raytracer, 1));
+}
+
+#[test]
+fn rasterizer_normal_maps() {
+    let dimpled = Material::new(Color::RED)
+        .with_specular(50.0)
+        .with_normal_map(dimpled_normals());
+    let bricks = |color| {
+        Material::new(color)
+            .with_specular(50.0)
+            .with_bump_map(brick_heights(), 3.0)
+    };
+    let materials = vec![
+        dimpled.clone(),
+        bricks(Color::GREEN),
+        dimpled.clone(),
+        bricks(Color::YELLOW),
+        dimpled,
+        bricks(Color::CYAN),
+    ];
+
+    let rasterizer = Rasterizer::default_scene()
+        .with_shading_model(ShadingModel::Phong)
+        .with_materials(materials);
+    check("rasterizer_normal_maps", &render(&rasterizer, 1));
+}
+
+// octagonal prism without normals, with its caps as single polygons and a negative index
+fn octagonal_prism() -> String {
+    let mut obj = String::from("# prism\n");
+    for y in [-1.0, 1.0] {
+        for i in 0..8 {
+            let angle = i as f64 * std::f64::consts::PI / 4.0;
+            obj += &format!("v {} {y} {}\n", angle.cos(), angle.sin());
+        }
+    }
+    for i in 1..=8 {
+        let next = i % 8 + 1;
+        obj += &format!("f {i} {next} {} {}\n", next + 8, i + 8);
+    }
+    obj += "f 8 7 6 5 4 3 2 1\nf 9 10 11 12 13 14 15 -1\n";
+    obj
+}
+
+// the same prism smoothed round its sides and left faceted, next to the default scene
+#[test]
+fn rasterizer_obj_normals() {
+    let rasterizer = Rasterizer::default_scene()
+        .with_obj(&octagonal_prism(), 3, 60.0, Vec3(-2.2, 1.9, 7.5), 0.5)
+        .unwrap()
+        .with_obj(&octagonal_prism(), 5, 30.0, Vec3(-1.0, 1.9, 7.5), 0.5)
+        .unwrap();
+    check("rasterizer_obj_normals", &render(&rasterizer, 1));
+
+    for (source, message) in [
+        ("v 0 0 0\nf 1 2 3\n", "obj: line 2: bad index"),
+        ("v 0 0\n", "obj: line 1: expected three coordinates"),
+        ("v 0 0 0\n", "obj: line 1: no faces"),
+    ] {
+        let error = Rasterizer::default_scene()
+            .with_obj(source, 0, 60.0, Vec3(0.0, 0.0, 5.0), 1.0)
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), message);
+    }
+}
+
+// every generator, dimpled to show off its texture coordinates and tangents
+#[test]
+fn rasterizer_generators() {
+    let colors = [
+        Color::RED,
+        Color::GREEN,
+        Color::BLUE,
+        Color::YELLOW,
+        Color::PURPLE,
+        Color::CYAN,
+        Color(200, 120, 60),
+    ];
+    let materials = colors
+        .map(|color| {
+            Material::new(color)
+                .with_specular(50.0)
+                .with_normal_map(dimpled_normals())
+        })
+        .to_vec();
+    let lights = vec![
+        RasterizerLight::Ambient {
+            intensity: Rgb::gray(0.2),
+        },
+        RasterizerLight::Directional {
+            direction: Vec3(-1.0, 1.0, -1.0),
+            intensity: Rgb::gray(0.8),
+        },
+    ];
+    let hills = |x: f64, z: f64| 0.3 * (x * 2.0).sin() * (z * 2.0).cos();
+
+    let mut rasterizer = Rasterizer::new(materials, lights)
+        .with_background(Background::Solid(Rgb(0.5, 0.7, 1.0)))
+        .with_model(Model::icosphere(2, 0), Vec3(-2.6, 2.4, 9.0), 0.9)
+        .with_model(Model::cylinder(16, 1), Vec3(0.0, 2.4, 9.0), 0.8)
+        .with_model(Model::cone(16, 2), Vec3(2.6, 2.4, 9.0), 0.9)
+        .with_model(Model::torus(0.35, 24, 3), Vec3(-2.6, 0.0, 9.0), 0.9)
+        .with_model(Model::capsule(1.0, 16, 4), Vec3(0.0, 0.0, 9.0), 0.7)
+        .with_model(Model::cube([5; 6]), Vec3(2.6, 0.0, 9.0), 0.7)
+        .with_model(Model::grid(2.0, 2.0, 4, 6), Vec3(-1.3, -2.6, 9.0), 1.0)
+        .with_model(
+            Model::heightfield(2.0, 2.0, 16, hills, 1),
+            Vec3(1.3, -2.6, 9.0),
+            1.0,
+        );
+    // looking down a little, so the torus and the terrain aren't edge on
+    rasterizer.translate(Vec3(0.0, 3.0, 0.0));
+    rasterizer.rotate(0.0, -20.0);
+    check("rasterizer_generators", &render(&rasterizer, 1));
+}
+
+#[test]
+fn rasterizer_draw_modes() {
+    for (name, mode) in [
+        ("rasterizer_wireframe", DrawMode::Wireframe),
+        ("rasterizer_hidden_line", DrawMode::HiddenLine),
+        ("rasterizer_points", DrawMode::Points),
+        ("rasterizer_shaded_wireframe", DrawMode::ShadedWireframe),
+    ] {
+        let rasterizer = Rasterizer::default_scene().with_draw_mode(mode);
+        check(name, &render(&rasterizer, 1));
+    }
+
+    // cycles through every mode and back
+    let mut rasterizer = Rasterizer::default_scene();
+    for _ in 0..5 {
+        rasterizer.next_draw_mode();
+    }
+    check("rasterizer_phong", &render(&rasterizer, 1));
+}
+
+// every 2D shape, straight on the canvas
+#[test]
+fn canvas_drawing() {
+    let mut canvas = Canvas::headless(SIZE, SIZE);
+
+    canvas.fill_rect((-46, 22), 40, 22, Color(250, 220, 120));
+    canvas.draw_rect((-46, 22), 40, 22, Color::BLACK);
+    canvas.fill_circle((26, 32), 12, Color::CYAN);
+    canvas.draw_circle((26, 32), 15, Color::BLUE);
+
+    // winding either way fills the same
+    canvas.fill_triangle((-44, -8), (-10, -8), (-27, 16), Color::GREEN);
+    canvas.fill_triangle((8, 16), (42, 16), (25, -8), Color::RED);
+    canvas.draw_triangle((8, 16), (42, 16), (25, -8), Color::BLACK);
+
+    canvas.draw_polyline(
+        &[(-44, -20), (-30, -32), (-16, -20), (-2, -32), (12, -20)],
+        Color::PURPLE,
+    );
+    canvas.draw_thick_line((-44, -42), (40, -28), 5.0, Color(60, 60, 60));
+    canvas.draw_line((20, -44), (44, -14), Color::BLUE);
+
+    // only the inside of the outline, which stops it
+    canvas.draw_circle((-26, 33), 6, Color::BLACK);
+    canvas.flood_fill((-26, 33), Color::RED);
+    canvas.flood_fill((0, 0), Color(230, 240, 255));
+
+    check("canvas_drawing", &canvas);
+}